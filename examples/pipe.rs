@@ -0,0 +1,29 @@
+// Copyright (c) 2020 Allen Wild
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Example of sending log output through a pipe to another process. Here the other process is
+//! `cat`, whose output is piped back and read by this one.
+
+use std::io::{self, BufRead, BufReader};
+use std::process::{Command, Stdio};
+
+use yall::log_macros::*;
+use yall::Logger;
+
+fn main() -> io::Result<()> {
+    let mut child = Command::new("cat").stdin(Stdio::piped()).stdout(Stdio::piped()).spawn()?;
+    let writer = child.stdin.take().unwrap();
+    let reader = child.stdout.take().unwrap();
+    Logger::new().no_header(true).with_pipe_writer(writer).init();
+
+    info!("hello through a pipe");
+    warn!("warnings work too");
+    error!("and errors");
+
+    // the logger holds the write end of the pipe forever, so read a known number of lines rather
+    // than waiting for EOF
+    for line in BufReader::new(reader).lines().take(3) {
+        println!("from pipe: {}", line?);
+    }
+    Ok(())
+}
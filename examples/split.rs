@@ -0,0 +1,25 @@
+// Copyright (c) 2020 Allen Wild
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Example of sending errors and warnings to stderr and everything else to stdout, so that e.g.
+//! `split > out.log` shows only problems on the terminal.
+
+use std::io::{self, IsTerminal};
+
+use yall::log_macros::*;
+use yall::termcolor::{ColorChoice, StandardStream};
+use yall::{Level, Logger};
+
+fn main() {
+    // termcolor's Auto doesn't check for a tty, so do that here like yall does for stderr
+    let choice = |tty: bool| if tty { ColorChoice::Auto } else { ColorChoice::Never };
+    let stderr = StandardStream::stderr(choice(io::stderr().is_terminal()));
+    let stdout = StandardStream::stdout(choice(io::stdout().is_terminal()));
+
+    Logger::with_priority_output(stderr, stdout, Level::Warn).no_header(true).verbose(1).init();
+
+    debug!("squash these bugs");
+    info!("some normal information");
+    warn!("oh deer");
+    error!("gosh heckie");
+}
@@ -0,0 +1,219 @@
+// Copyright (c) 2020 Allen Wild
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! A non-blocking logger which hands records off to a separate worker for writing.
+//!
+//! [`AsyncLogger`] is registered as the global logger, and each log call only formats the message
+//! and sends it through a channel. The [`LogWorker`] receives messages and writes them using a
+//! regular [`Logger`], so the output looks exactly the same. The worker isn't started
+//! automatically; spawn it on a thread with [`LogWorker::run`], or on a tokio runtime with
+//! `LogWorker::run_async` (requires the `tokio` feature).
+//!
+//! By default the channel is unbounded, so a worker which falls behind can use a lot of memory.
+//! Use [`Logger::with_backpressure_limit`] to bound it.
+//!
+//! ```no_run
+//! use yall::async_logger::AsyncLogger;
+//! use yall::LevelFilter;
+//!
+//! let (logger, worker) = AsyncLogger::new(LevelFilter::Info);
+//! std::thread::spawn(move || worker.run());
+//! logger.init();
+//! ```
+
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender, TrySendError};
+use std::sync::Arc;
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use crate::{ConfigError, LogState, Logger};
+
+/// An owned copy of a log record, since `log::Record` borrows its contents.
+#[derive(Debug)]
+struct OwnedRecord {
+    level: Level,
+    target: String,
+    module_path: Option<String>,
+    file: Option<String>,
+    line: Option<u32>,
+    message: String,
+}
+
+#[derive(Debug)]
+enum Message {
+    Record(OwnedRecord),
+    Flush,
+}
+
+/// What an [`AsyncLogger`] does with a record when its queue is full, see
+/// [`Logger::with_backpressure_limit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BackpressurePolicy {
+    /// Wait until the worker makes room in the queue, slowing down the logging thread.
+    Block,
+    /// Discard the record and count it in [`LogState::dropped_records`].
+    Drop,
+}
+
+#[derive(Debug)]
+enum Channel {
+    Unbounded(Sender<Message>),
+    Bounded(SyncSender<Message>, BackpressurePolicy),
+}
+
+/// The logging frontend, which implements [`Log`] by sending records to a [`LogWorker`].
+#[derive(Debug)]
+pub struct AsyncLogger {
+    state: Arc<LogState>,
+    tx: Channel,
+}
+
+/// The logging backend, which writes the records sent by an [`AsyncLogger`].
+#[derive(Debug)]
+pub struct LogWorker {
+    logger: Logger,
+    rx: Receiver<Message>,
+}
+
+impl AsyncLogger {
+    /// Create an AsyncLogger and LogWorker pair with the given level and default settings.
+    pub fn new(level: LevelFilter) -> (AsyncLogger, LogWorker) {
+        // the default configuration is always valid, so there's no need to build it
+        Self::pair(Logger::with_level(level))
+    }
+
+    /// Create an AsyncLogger and LogWorker pair, where the worker writes records using `logger`.
+    /// The logger's level is also used to filter records before they're sent.
+    ///
+    /// The logger's configuration is checked with [`Logger::build`] first, returning an error if
+    /// it's invalid.
+    pub fn with_logger(logger: Logger) -> Result<(AsyncLogger, LogWorker), ConfigError> {
+        Ok(Self::pair(logger.build()?))
+    }
+
+    /// Connect a worker using an already built `logger` to a new AsyncLogger.
+    fn pair(mut logger: Logger) -> (AsyncLogger, LogWorker) {
+        logger.initialized = true;
+        let (tx, rx) = match logger.backpressure {
+            Some((max_queue, policy)) => {
+                let (tx, rx) = mpsc::sync_channel(max_queue);
+                (Channel::Bounded(tx, policy), rx)
+            }
+            None => {
+                let (tx, rx) = mpsc::channel();
+                (Channel::Unbounded(tx), rx)
+            }
+        };
+        let state = Arc::clone(&logger.state);
+        (AsyncLogger { state, tx }, LogWorker { logger, rx })
+    }
+
+    /// Register this as the global logger with the [`log`](::log) crate. May fail if the
+    /// application has already set a logger.
+    pub fn try_init(self) -> Result<(), ConfigError> {
+        log::set_max_level(self.state.max_level());
+        Ok(log::set_boxed_logger(Box::new(self))?)
+    }
+
+    /// Same as [`try_init`](Self::try_init), but on success return a handle to the worker
+    /// logger's [`LogState`], e.g. for checking [`LogState::dropped_records`].
+    pub fn try_init_and_return(self) -> Result<Arc<LogState>, ConfigError> {
+        let state = Arc::clone(&self.state);
+        self.try_init()?;
+        Ok(state)
+    }
+
+    /// Same as [`try_init`](Self::try_init) but panic on failure.
+    pub fn init(self) {
+        self.try_init().expect("failed to initialize logger");
+    }
+
+    /// Send a message to the worker, applying the backpressure policy. Returns false if the
+    /// message was dropped because the queue is full.
+    fn send(&self, msg: Message) -> bool {
+        // Other errors mean that the worker has gone away, in which case there's nothing useful
+        // to do with the message.
+        match &self.tx {
+            Channel::Unbounded(tx) => {
+                let _ = tx.send(msg);
+            }
+            Channel::Bounded(tx, BackpressurePolicy::Block) => {
+                let _ = tx.send(msg);
+            }
+            Channel::Bounded(tx, BackpressurePolicy::Drop) => {
+                if let Err(TrySendError::Full(_)) = tx.try_send(msg) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+impl Log for AsyncLogger {
+    fn enabled(&self, m: &Metadata) -> bool {
+        // Records are only fully filtered by the worker's logger, this just drops records which
+        // can't possibly be enabled by the global level or any module level. The level is read
+        // from the shared state each time so that LogState::set_level takes effect.
+        m.level() <= self.state.max_level()
+    }
+
+    fn log(&self, r: &Record) {
+        if !self.enabled(r.metadata()) {
+            return;
+        }
+
+        let record = OwnedRecord {
+            level: r.level(),
+            target: r.target().to_owned(),
+            module_path: r.module_path().map(str::to_owned),
+            file: r.file().map(str::to_owned),
+            line: r.line(),
+            message: r.args().to_string(),
+        };
+        if !self.send(Message::Record(record)) {
+            self.state.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn flush(&self) {
+        // a dropped flush doesn't matter, the worker is busy writing records anyway
+        self.send(Message::Flush);
+    }
+}
+
+impl LogWorker {
+    /// Write records as they're received. Blocks until every [`AsyncLogger`] sending to this
+    /// worker has been dropped, which in practice is never for the global logger.
+    pub fn run(self) {
+        for msg in self.rx.iter() {
+            match msg {
+                Message::Record(r) => self.logger.log(
+                    &Record::builder()
+                        .level(r.level)
+                        .target(&r.target)
+                        .module_path(r.module_path.as_deref())
+                        .file(r.file.as_deref())
+                        .line(r.line)
+                        .args(format_args!("{}", r.message))
+                        .build(),
+                ),
+                Message::Flush => self.logger.flush(),
+            }
+        }
+    }
+
+    /// Run the worker on tokio's blocking thread pool, completing when [`run`](Self::run) would
+    /// return. Requires the `tokio` feature.
+    #[cfg(feature = "tokio")]
+    pub async fn run_async(self) {
+        // pass along a panic from the worker, a cancellation can only happen if the runtime is
+        // shutting down anyway.
+        if let Err(e) = tokio::task::spawn_blocking(move || self.run()).await {
+            if let Ok(panic) = e.try_into_panic() {
+                std::panic::resume_unwind(panic);
+            }
+        }
+    }
+}
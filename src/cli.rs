@@ -0,0 +1,79 @@
+// Copyright (c) 2020 Allen Wild
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Command-line options for configuring a [`Logger`], for use with clap's derive API.
+//!
+//! Flatten [`LogArgs`] into an application's arguments struct to get yall's standard options.
+//!
+//! ```no_run
+//! use clap::Parser;
+//!
+//! #[derive(Parser)]
+//! struct Args {
+//!     #[command(flatten)]
+//!     log: yall::cli::LogArgs,
+//! }
+//!
+//! let args = Args::parse();
+//! args.log.to_logger().init();
+//! ```
+//!
+//! For applications using clap's builder API, [`Logger::with_level_from_clap_matches`] reads the
+//! verbose and quiet counts from [`ArgMatches`] instead.
+
+use clap::{ArgAction, ArgMatches};
+
+use crate::{ColorMode, Logger};
+
+/// yall's logging options: `--verbose`, `--quiet`, `--log-level`, `--color`, and
+/// `--full-filename`.
+#[derive(Debug, Clone, clap::Args)]
+pub struct LogArgs {
+    /// Be more verbose, can be given multiple times
+    #[arg(short, long, action = ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+
+    /// Be more quiet, can be given multiple times
+    #[arg(short, long, action = ArgAction::Count)]
+    pub quiet: u8,
+
+    /// Set the numeric log level, from 0=off to 5=trace
+    #[arg(long, value_name = "LEVEL", conflicts_with_all = ["verbose", "quiet"])]
+    pub log_level: Option<u8>,
+
+    /// When to use colored output
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = ColorMode::Auto)]
+    pub color: ColorMode,
+
+    /// Show the full non-abbreviated filename in debug/trace logs
+    #[arg(long)]
+    pub full_filename: bool,
+}
+
+impl LogArgs {
+    /// Create a Logger configured from these arguments.
+    pub fn to_logger(&self) -> Logger {
+        let logger = match self.log_level {
+            Some(level) => Logger::with_verbosity(level),
+            None => Logger::new().verbose(self.verbose).quiet(self.quiet),
+        };
+        logger.color(self.color).full_filename(self.full_filename)
+    }
+}
+
+impl Logger {
+    /// Create a Logger with its level set from the number of verbose and quiet flags in clap's
+    /// parsed arguments, where `verbose_id` and `quiet_id` are the IDs of arguments using
+    /// [`ArgAction::Count`]. This is the same as
+    /// `Logger::new().verbose(matches.get_count(verbose_id)).quiet(matches.get_count(quiet_id))`.
+    ///
+    /// Panics if either argument isn't defined or isn't a count, as
+    /// [`ArgMatches::get_count`] does.
+    pub fn with_level_from_clap_matches(
+        matches: &ArgMatches,
+        verbose_id: &str,
+        quiet_id: &str,
+    ) -> Logger {
+        Logger::new().verbose(matches.get_count(verbose_id)).quiet(matches.get_count(quiet_id))
+    }
+}
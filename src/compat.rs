@@ -0,0 +1,39 @@
+// Copyright (c) 2020 Allen Wild
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Helpers for migrating from other logging crates.
+//!
+//! [`EnvLoggerCompat`] configures a [`Logger`] from the same environment variables as
+//! `env_logger`, so that existing `RUST_LOG` settings keep working. The main differences from
+//! `env_logger` are:
+//!
+//!   * The output format is yall's, not `env_logger`'s, and there's no timestamp unless one is
+//!     added with [`Logger::with_custom_time_source`].
+//!   * Regex message filters (`/pattern` at the end of `RUST_LOG`) aren't supported and are
+//!     ignored, see [`env_filter`](crate::env_filter).
+//!   * `RUST_LOG_STYLE` only supports `auto`, `always`, and `never`. Unknown values are treated as
+//!     `auto`, same as `env_logger`.
+
+use log::LevelFilter;
+
+use crate::{ColorMode, EnvFilter, Logger};
+
+/// Create a [`Logger`] configured like `env_logger`.
+#[derive(Debug)]
+pub struct EnvLoggerCompat;
+
+impl EnvLoggerCompat {
+    /// Create a Logger from the `RUST_LOG` and `RUST_LOG_STYLE` environment variables, like
+    /// `env_logger::Builder::from_default_env()`. As with `env_logger`, only errors are shown if
+    /// `RUST_LOG` doesn't set a global level, and yall's startup header is disabled.
+    pub fn from_env() -> Logger {
+        let color = match std::env::var("RUST_LOG_STYLE").as_deref() {
+            Ok("always") => ColorMode::Always,
+            Ok("never") => ColorMode::Never,
+            _ => ColorMode::Auto,
+        };
+        Logger::with_level_and_color(LevelFilter::Error, color)
+            .env_filter(EnvFilter::from_default_env())
+            .no_header(true)
+    }
+}
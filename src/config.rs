@@ -0,0 +1,260 @@
+// Copyright (c) 2020 Allen Wild
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Saving and loading a Logger's basic settings as a small TOML file.
+//!
+//! Only a flat `[logger]` table of strings and booleans is supported, which is all yall needs, so
+//! this is parsed by hand rather than pulling in serde and a TOML crate. The supported subset is
+//! documented on [`Logger::load_config`].
+
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use log::LevelFilter;
+
+use crate::{ColorMode, LevelFilterExt, Logger, SourceDisplayMode};
+
+impl Logger {
+    /// Save this Logger's basic settings (level, color mode, and a few formatting options) to a
+    /// TOML file under a `[logger]` section, so that interactive tools can persist user
+    /// preferences. Load it back with [`load_config`](Self::load_config).
+    ///
+    /// Settings which can't be represented in a config file, such as writers, hooks, and module
+    /// levels, are not saved.
+    pub fn persist_config(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let color = match self.color_mode {
+            ColorMode::Auto => "auto",
+            ColorMode::Always => "always",
+            ColorMode::Never => "never",
+        };
+        let text = format!(
+            "[logger]\n\
+             level = \"{}\"\n\
+             color = \"{}\"\n\
+             full_filename = {}\n\
+             header = {}\n\
+             colorize_brackets = {}\n\
+             dim_location = {}\n",
+            self.level().as_lower_str(),
+            color,
+            self.format.source_display == SourceDisplayMode::FullPath,
+            self.header,
+            self.format.brackets_only,
+            self.format.dim_location,
+        );
+        fs::write(path, text)
+    }
+
+    /// Create a Logger from a config file written by [`persist_config`](Self::persist_config).
+    /// If the file doesn't exist, the default Logger is returned. Any keys missing from the file
+    /// keep their default values, while unknown keys or invalid values are an error.
+    ///
+    /// The file is parsed with a small built-in parser which supports the subset of TOML that
+    /// these settings need: `[table]` headers, bare `key = value` pairs, basic (`"..."`) strings
+    /// with the usual escapes, literal (`'...'`) strings, booleans, and `#` comments, including
+    /// at the end of a line. Only the `[logger]` table is read, and other tables are ignored, but
+    /// things like quoted keys, dotted keys, and multi-line strings aren't supported.
+    pub fn load_config(path: impl AsRef<Path>) -> Result<Logger, Box<dyn Error>> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Logger::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut logger = Logger::new();
+        let mut in_logger = false;
+        for (i, line) in text.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(table) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                // other tables may belong to the application, only ours is parsed
+                in_logger = table.trim() == "logger";
+                continue;
+            }
+            if !in_logger {
+                continue;
+            }
+
+            let (key, value) = match line.split_once('=') {
+                Some((k, v)) => (k.trim(), v.trim()),
+                None => return Err(format!("line {}: expected `key = value`", i + 1).into()),
+            };
+            let string = || match parse_string(value) {
+                Some(s) => Ok(s),
+                None => Err(format!("line {}: expected a string for {}", i + 1, key)),
+            };
+            let boolean = || match value {
+                "true" => Ok(true),
+                "false" => Ok(false),
+                _ => Err(format!("line {}: expected a boolean for {}", i + 1, key)),
+            };
+
+            logger = match key {
+                "level" => {
+                    let level: LevelFilter = string()?
+                        .parse()
+                        .map_err(|_| format!("line {}: invalid level {}", i + 1, value))?;
+                    logger.state.store_level(level);
+                    logger
+                }
+                "color" => match string()?.as_str() {
+                    "auto" => logger.color(ColorMode::Auto),
+                    "always" => logger.color(ColorMode::Always),
+                    "never" => logger.color(ColorMode::Never),
+                    _ => return Err(format!("line {}: invalid color {}", i + 1, value).into()),
+                },
+                "full_filename" => logger.full_filename(boolean()?),
+                "header" => logger.no_header(!boolean()?),
+                "colorize_brackets" => logger.colorize_brackets(boolean()?),
+                "dim_location" => logger.dim_location(boolean()?),
+                _ => return Err(format!("line {}: unknown key {}", i + 1, key).into()),
+            };
+        }
+        Ok(logger)
+    }
+}
+
+/// Remove a `#` comment from the end of a line, ignoring any `#` inside a string.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            // backslash escapes only exist in basic strings
+            (Some('"'), _) if escaped => escaped = false,
+            (Some('"'), '\\') => escaped = true,
+            (Some(q), c) if c == q => quote = None,
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '#') => return &line[..i],
+            _ => (),
+        }
+    }
+    line
+}
+
+/// Parse a basic or literal TOML string, returning None if it's not a valid string.
+fn parse_string(value: &str) -> Option<String> {
+    if let Some(s) = value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')) {
+        return if s.contains('\'') { None } else { Some(s.to_owned()) };
+    }
+    let s = value.strip_prefix('"')?.strip_suffix('"')?;
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.push(match chars.next()? {
+                'b' => '\u{8}',
+                't' => '\t',
+                'n' => '\n',
+                'f' => '\u{c}',
+                'r' => '\r',
+                '"' => '"',
+                '\\' => '\\',
+                'u' => unicode_escape(&mut chars, 4)?,
+                'U' => unicode_escape(&mut chars, 8)?,
+                _ => return None,
+            }),
+            // an unescaped quote would have ended the string
+            '"' => return None,
+            c => out.push(c),
+        }
+    }
+    Some(out)
+}
+
+/// Parse the hex digits of a `\uXXXX` or `\UXXXXXXXX` escape.
+fn unicode_escape(chars: &mut std::str::Chars, len: usize) -> Option<char> {
+    let hex: String = chars.take(len).collect();
+    if hex.len() != len {
+        return None;
+    }
+    char::from_u32(u32::from_str_radix(&hex, 16).ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comments() {
+        assert_eq!(strip_comment("level = \"info\" # comment"), "level = \"info\" ");
+        assert_eq!(strip_comment("# whole line"), "");
+        assert_eq!(strip_comment("color = \"#never\""), "color = \"#never\"");
+        assert_eq!(strip_comment("a = 'x#' # y"), "a = 'x#' ");
+        assert_eq!(strip_comment(r##"a = "\"#" # y"##), r##"a = "\"#" "##);
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(parse_string("\"info\"").as_deref(), Some("info"));
+        assert_eq!(parse_string("'C:\\logs'").as_deref(), Some("C:\\logs"));
+        assert_eq!(parse_string(r#""a\tb\"c\\""#).as_deref(), Some("a\tb\"c\\"));
+        assert_eq!(parse_string(r#""\u00e9\U0001F600""#).as_deref(), Some("é😀"));
+        assert_eq!(parse_string("info"), None);
+        assert_eq!(parse_string("\"unterminated"), None);
+        assert_eq!(parse_string(r#""a"b""#), None);
+        assert_eq!(parse_string(r#""\q""#), None);
+        assert_eq!(parse_string(r#""\u12""#), None);
+        assert_eq!(parse_string("'it's'"), None);
+    }
+
+    /// Write `text` to a temporary file and load it.
+    fn load_text(name: &str, text: &str) -> Result<Logger, Box<dyn Error>> {
+        let path =
+            std::env::temp_dir().join(format!("yall-test-{}-{}.toml", name, std::process::id()));
+        fs::write(&path, text).unwrap();
+        let res = Logger::load_config(&path);
+        let _ = fs::remove_file(&path);
+        res
+    }
+
+    #[test]
+    fn load() {
+        let text = "# settings\n\
+                    [other]\n\
+                    level = 'bogus'\n\
+                    \n\
+                    [ logger ]\n\
+                    level = \"debug\" # more output\n\
+                    color = 'never'\n\
+                    header = false\n";
+        let logger = load_text("load", text).unwrap();
+        assert_eq!(logger.level(), LevelFilter::Debug);
+        assert_eq!(logger.color_mode, ColorMode::Never);
+        assert!(!logger.header);
+    }
+
+    #[test]
+    fn load_errors() {
+        assert!(load_text("no-equals", "[logger]\nlevel\n").is_err());
+        assert!(load_text("unknown-key", "[logger]\nverbosity = 3\n").is_err());
+        assert!(load_text("bad-level", "[logger]\nlevel = \"loud\"\n").is_err());
+        assert!(load_text("bare-string", "[logger]\nlevel = info\n").is_err());
+        assert!(load_text("bad-bool", "[logger]\nheader = yes\n").is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = std::env::temp_dir().join(format!("yall-test-rt-{}.toml", std::process::id()));
+        Logger::with_level(LevelFilter::Trace)
+            .color(ColorMode::Always)
+            .colorize_brackets(true)
+            .persist_config(&path)
+            .unwrap();
+        let logger = Logger::load_config(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(logger.level(), LevelFilter::Trace);
+        assert_eq!(logger.color_mode, ColorMode::Always);
+        assert!(logger.format.brackets_only);
+    }
+
+    #[test]
+    fn missing_file() {
+        let path = std::env::temp_dir().join("yall-test-does-not-exist.toml");
+        assert_eq!(Logger::load_config(path).unwrap().level(), LevelFilter::Info);
+    }
+}
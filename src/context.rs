@@ -0,0 +1,39 @@
+// Copyright (c) 2020 Allen Wild
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Visual separators between independent units of work, like requests, files, or jobs.
+//!
+//! [`begin_section`] writes a line like `════ label ════` and [`end_section`] writes a matching
+//! `════ end label ════`. Sections can be nested. Separators are always written regardless of the
+//! log level, and are as wide as the logger's max line width, or 80 columns by default. Use
+//! [`Logger::with_context_separator`] to change the line character.
+//!
+//! These write directly to the yall [`Logger`] registered with [`Logger::init`] or
+//! [`Logger::try_init`], and do nothing if there isn't one. They aren't log records, so other
+//! [`Log`](log::Log) implementations never see them. To use sections with a Logger that's
+//! registered some other way, e.g. with [`Logger::into_arc`], call [`Logger::begin_section`] and
+//! [`Logger::end_section`] on it directly.
+//!
+//! [`Logger`]: crate::Logger
+//! [`Logger::with_context_separator`]: crate::Logger::with_context_separator
+//! [`Logger::init`]: crate::Logger::init
+//! [`Logger::try_init`]: crate::Logger::try_init
+//! [`Logger::into_arc`]: crate::Logger::into_arc
+//! [`Logger::begin_section`]: crate::Logger::begin_section
+//! [`Logger::end_section`]: crate::Logger::end_section
+
+/// Start a section, writing a separator line with `label` centered in it.
+pub fn begin_section(label: &str) {
+    if let Some(logger) = crate::registered_logger() {
+        // errors are ignored like with the header, since sections are purely decorative
+        let _ = logger.begin_section(label);
+    }
+}
+
+/// End the most recently started section, writing a matching separator line. Does nothing if no
+/// section is open.
+pub fn end_section() {
+    if let Some(logger) = crate::registered_logger() {
+        let _ = logger.end_section();
+    }
+}
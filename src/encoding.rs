@@ -0,0 +1,50 @@
+// Copyright (c) 2020 Allen Wild
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Output encodings other than UTF-8, for
+//! [`Logger::with_output_encoding`](crate::Logger::with_output_encoding).
+
+use std::borrow::Cow;
+use std::convert::TryFrom;
+
+use encoding_rs::{EncoderResult, WINDOWS_1252};
+
+/// The character encoding used for log output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum OutputEncoding {
+    /// UTF-8, which is what Rust strings already are.
+    #[default]
+    Utf8,
+    /// ISO-8859-1, where each character up to U+00FF is a single byte.
+    Latin1,
+    /// Windows code page 1252, used by many legacy Windows consoles.
+    Windows1252,
+}
+
+/// Convert UTF-8 text to `enc`, replacing characters which can't be represented with `?`.
+pub(crate) fn transcode(text: &str, enc: OutputEncoding) -> Cow<'_, [u8]> {
+    match enc {
+        OutputEncoding::Utf8 => Cow::Borrowed(text.as_bytes()),
+        // encoding_rs follows the WHATWG standard, which treats Latin1 labels as Windows-1252, so
+        // do real Latin1 by hand. It's trivial anyway.
+        OutputEncoding::Latin1 => {
+            Cow::Owned(text.chars().map(|c| u8::try_from(c).unwrap_or(b'?')).collect())
+        }
+        OutputEncoding::Windows1252 => {
+            let mut encoder = WINDOWS_1252.new_encoder();
+            let mut out = Vec::with_capacity(text.len());
+            let mut src = text;
+            loop {
+                let (result, read) =
+                    encoder.encode_from_utf8_to_vec_without_replacement(src, &mut out, true);
+                src = &src[read..];
+                match result {
+                    EncoderResult::InputEmpty => break,
+                    EncoderResult::OutputFull => out.reserve(src.len()),
+                    EncoderResult::Unmappable(_) => out.push(b'?'),
+                }
+            }
+            Cow::Owned(out)
+        }
+    }
+}
@@ -0,0 +1,120 @@
+// Copyright (c) 2020 Allen Wild
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Parsing for `env_logger`-style `RUST_LOG` filter directives.
+//!
+//! A filter is a comma-separated list of directives, each of which is one of
+//!
+//!   * `level`: set the global log level, e.g. `debug`
+//!   * `module=level`: set the level for a module and its children, e.g. `hyper=warn`
+//!   * `module`: enable all logging for a module, same as `module=trace`
+//!
+//! Use the result with [`Logger::env_filter`](crate::Logger::env_filter). Unlike `env_logger`,
+//! regex message filters (`/pattern` at the end of the filter) aren't supported and are ignored.
+//! Invalid directives are skipped.
+
+use std::str::FromStr;
+
+use log::LevelFilter;
+
+/// A single filter directive: an optional module path and the level for it. A directive without
+/// a module sets the global level.
+pub type Directive = (Option<String>, LevelFilter);
+
+/// A parsed set of `RUST_LOG`-style filter directives.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnvFilter {
+    directives: Vec<Directive>,
+}
+
+impl EnvFilter {
+    /// Parse the `RUST_LOG` environment variable. If it's not set, the filter is empty.
+    pub fn from_default_env() -> EnvFilter {
+        Self::from_env("RUST_LOG")
+    }
+
+    /// Parse the environment variable `var`. If it's not set, the filter is empty.
+    pub fn from_env(var: &str) -> EnvFilter {
+        std::env::var(var).map(|v| Self::parse(&v)).unwrap_or_default()
+    }
+
+    /// Parse a filter string, e.g. `info,hyper=warn,myapp::db=trace`.
+    pub fn parse(spec: &str) -> EnvFilter {
+        // ignore any regex filter after a slash
+        let spec = spec.split('/').next().unwrap_or_default();
+
+        let mut directives = Vec::new();
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let directive = match part.split_once('=') {
+                Some((module, level)) => match LevelFilter::from_str(level.trim()) {
+                    // an empty module would be rejected by Logger::build
+                    Ok(_) if module.trim().is_empty() => continue,
+                    Ok(level) => (Some(module.trim().to_owned()), level),
+                    Err(_) => continue,
+                },
+                None => match LevelFilter::from_str(part) {
+                    Ok(level) => (None, level),
+                    Err(_) => (Some(part.to_owned()), LevelFilter::Trace),
+                },
+            };
+            directives.push(directive);
+        }
+        EnvFilter { directives }
+    }
+
+    /// The parsed directives, in the order they were given.
+    pub fn directives(&self) -> &[Directive] {
+        &self.directives
+    }
+
+    /// Convert into the list of parsed directives.
+    pub fn into_directives(self) -> Vec<Directive> {
+        self.directives
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(spec: &str) -> Vec<Directive> {
+        EnvFilter::parse(spec).into_directives()
+    }
+
+    fn module(name: &str, level: LevelFilter) -> Directive {
+        (Some(String::from(name)), level)
+    }
+
+    #[test]
+    fn global_level() {
+        assert_eq!(parse("debug"), [(None, LevelFilter::Debug)]);
+        assert_eq!(parse("WARN"), [(None, LevelFilter::Warn)]);
+        assert_eq!(parse("off"), [(None, LevelFilter::Off)]);
+    }
+
+    #[test]
+    fn module_levels() {
+        assert_eq!(
+            parse("info, hyper=warn ,myapp::db = trace"),
+            [
+                (None, LevelFilter::Info),
+                module("hyper", LevelFilter::Warn),
+                module("myapp::db", LevelFilter::Trace),
+            ]
+        );
+        assert_eq!(parse("myapp"), [module("myapp", LevelFilter::Trace)]);
+    }
+
+    #[test]
+    fn invalid_directives_are_skipped() {
+        assert_eq!(parse("hyper=loud,=debug,warn"), [(None, LevelFilter::Warn)]);
+        assert_eq!(parse(""), []);
+        assert_eq!(parse(",,"), []);
+    }
+
+    #[test]
+    fn regex_filter_is_ignored() {
+        assert_eq!(parse("debug/some.*pattern"), [(None, LevelFilter::Debug)]);
+        assert_eq!(parse("/pattern"), []);
+    }
+}
@@ -0,0 +1,985 @@
+// Copyright (c) 2020 Allen Wild
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! yall's log record formatting, usable independently of [`Logger`](crate::Logger) through
+//! [`write_record`].
+
+use std::borrow::Cow;
+use std::io;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use log::{Level, Record};
+use termcolor::{Color, ColorSpec, WriteColor};
+
+/// A field of a formatted log line, used with
+/// [`Logger::with_field_order`](crate::Logger::with_field_order) to customize the output layout.
+///
+/// Bracketed fields (`Level`, `Target`, `Location`) are written next to each other with no space
+/// in between, all other fields are separated by a single space.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    /// The current UTC time in RFC 3339 format, e.g. `2020-06-01T12:34:56Z`, or the output of the
+    /// function set by
+    /// [`Logger::with_custom_time_source`](crate::Logger::with_custom_time_source).
+    Timestamp,
+    /// The level label, e.g. `[ERROR]`. By default Info messages have no level label, see
+    /// [`LevelLabels`].
+    Level,
+    /// The record's target (usually the module path), e.g. `[myapp::db]`.
+    Target,
+    /// The `[file:line]` source location. Only shown for Debug and Trace messages.
+    Location,
+    /// The log message itself.
+    Message,
+}
+
+/// How the source location is shown in the [`Field::Location`] field for Debug and Trace
+/// messages, see
+/// [`Logger::with_debug_source_display`](crate::Logger::with_debug_source_display).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SourceDisplayMode {
+    /// Don't show the location at all.
+    None,
+    /// Only the shortened filename, e.g. `[main]`.
+    FileOnly,
+    /// Only the line number, e.g. `[line 42]`.
+    LineOnly,
+    /// The shortened filename and line number, e.g. `[main:42]`, with any `src/` prefix and
+    /// `.rs` suffix removed.
+    #[default]
+    FileAndLine,
+    /// The full filename and line number, e.g. `[src/main.rs:42]`.
+    FullPath,
+    /// The module path and line number, e.g. `[myapp::db:42]`.
+    ModulePath,
+    /// The module path followed by the shortened filename and line number, e.g.
+    /// `[myapp::db db:42]`.
+    ModuleAndFile,
+}
+
+/// A simpler choice of what's shown in the location field, see
+/// [`Logger::location_display`](crate::Logger::location_display). Each variant is a shorthand for
+/// a [`SourceDisplayMode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum LocationDisplay {
+    /// The filename and line number, e.g. `[main:42]`. Same as
+    /// [`SourceDisplayMode::FileAndLine`].
+    #[default]
+    File,
+    /// The module path and line number, e.g. `[myapp::db:42]`, which is more stable than file
+    /// paths across refactors. Same as [`SourceDisplayMode::ModulePath`].
+    ModulePath,
+    /// Both the module path and filename, e.g. `[myapp::db db:42]`. Same as
+    /// [`SourceDisplayMode::ModuleAndFile`].
+    Both,
+    /// No location at all. Same as [`SourceDisplayMode::None`].
+    Neither,
+}
+
+impl From<LocationDisplay> for SourceDisplayMode {
+    fn from(d: LocationDisplay) -> Self {
+        match d {
+            LocationDisplay::File => SourceDisplayMode::FileAndLine,
+            LocationDisplay::ModulePath => SourceDisplayMode::ModulePath,
+            LocationDisplay::Both => SourceDisplayMode::ModuleAndFile,
+            LocationDisplay::Neither => SourceDisplayMode::None,
+        }
+    }
+}
+
+/// The default field order, which matches yall's traditional output format.
+const DEFAULT_FIELD_ORDER: [Field; 3] = [Field::Level, Field::Location, Field::Message];
+
+/// The set of colors used for each log level.
+///
+/// Start with [`LogColors::new`] for yall's default colors and customize individual levels with
+/// [`set`](Self::set), then use it with [`ColorScheme::custom`](crate::ColorScheme::custom).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogColors {
+    error: ColorSpec,
+    warn: ColorSpec,
+    info: ColorSpec,
+    debug: ColorSpec,
+    trace: ColorSpec,
+}
+
+impl Default for LogColors {
+    /// The default colors, same as [`LogColors::new`]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LogColors {
+    /// Create the default yall colors: bold red errors, bold yellow warnings, plain info, cyan
+    /// debug, and blue trace.
+    pub fn new() -> Self {
+        // The set_* functions return &mut, so we need to_owned() to convert back to an actual
+        // value. Since ColorSpec doesn't implement Copy, we can't just dereference.
+        let error = ColorSpec::new().set_fg(Some(Color::Red)).set_bold(true).to_owned();
+        let warn = ColorSpec::new().set_fg(Some(Color::Yellow)).set_bold(true).to_owned();
+        let info = ColorSpec::new();
+        let debug = ColorSpec::new().set_fg(Some(Color::Cyan)).to_owned();
+        let trace = ColorSpec::new().set_fg(Some(Color::Blue)).to_owned();
+
+        Self { error, warn, info, debug, trace }
+    }
+
+    /// Get the color used for a level.
+    pub fn get(&self, l: Level) -> &ColorSpec {
+        match l {
+            Level::Error => &self.error,
+            Level::Warn => &self.warn,
+            Level::Info => &self.info,
+            Level::Debug => &self.debug,
+            Level::Trace => &self.trace,
+        }
+    }
+
+    /// Set the color used for a level.
+    pub fn set(&mut self, l: Level, spec: ColorSpec) {
+        match l {
+            Level::Error => self.error = spec,
+            Level::Warn => self.warn = spec,
+            Level::Info => self.info = spec,
+            Level::Debug => self.debug = spec,
+            Level::Trace => self.trace = spec,
+        }
+    }
+
+    /// Whether every level uses the plain default [`ColorSpec`], i.e. nothing is colored.
+    pub fn is_colorless(&self) -> bool {
+        Level::iter().all(|l| *self.get(l) == ColorSpec::new())
+    }
+}
+
+/// How to handle newlines embedded in log messages, e.g. from `{:#?}` formatting.
+///
+/// With `PassThrough` and `Indent`, colored messages span multiple terminal lines, and the color
+/// is only reset at the end of the message. Some terminals and pagers only apply colors to the
+/// line where they were set, so use
+/// [`Logger::with_color_reset_on_each_line`](crate::Logger::with_color_reset_on_each_line) to
+/// re-apply the color on each line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NewlineMode {
+    /// Write newlines unchanged, so continuation lines start at the first column. This is the
+    /// default.
+    PassThrough,
+    /// Indent each continuation line by the given number of spaces.
+    Indent(usize),
+    /// Replace each newline with the given character, e.g. `'↵'`, keeping the message on one
+    /// line.
+    Replace(char),
+    /// Replace each newline with a literal `\n`, keeping the message on one line.
+    Escape,
+}
+
+impl Default for NewlineMode {
+    /// The default NewlineMode is `PassThrough`
+    fn default() -> Self {
+        Self::PassThrough
+    }
+}
+
+/// The label text shown in brackets for each log level. A level can have no label, in which case
+/// the level field is omitted entirely, which is the default for Info.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LevelLabels {
+    labels: [Option<String>; 5],
+}
+
+impl Default for LevelLabels {
+    /// The default labels, same as [`LevelLabels::new`]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LevelLabels {
+    /// Create a LevelLabels from a static label for each level, in order from Error to Trace.
+    fn from_static(labels: [Option<&'static str>; 5]) -> LevelLabels {
+        LevelLabels { labels: labels.map(|l| l.map(String::from)) }
+    }
+
+    /// The default labels: `ERROR`, `WARN`, `DEBUG`, and `TRACE`, with no label for Info.
+    pub fn new() -> LevelLabels {
+        Self::from_static([Some("ERROR"), Some("WARN"), None, Some("DEBUG"), Some("TRACE")])
+    }
+
+    /// Syslog-style severity names: `ERR`, `WARNING`, `NOTICE`, and `DEBUG` for both Debug and
+    /// Trace. Every level is labeled so that the output can be parsed by tools expecting syslog
+    /// severities.
+    pub fn syslog() -> LevelLabels {
+        Self::from_static([
+            Some("ERR"),
+            Some("WARNING"),
+            Some("NOTICE"),
+            Some("DEBUG"),
+            Some("DEBUG"),
+        ])
+    }
+
+    /// Three-character labels for every level: `ERR`, `WRN`, `INF`, `DBG`, and `TRC`. Since
+    /// they're all the same width, messages line up in columns.
+    pub fn three_char() -> LevelLabels {
+        Self::from_static([Some("ERR"), Some("WRN"), Some("INF"), Some("DBG"), Some("TRC")])
+    }
+
+    /// Get the label for a level, if it has one.
+    pub fn get(&self, level: Level) -> Option<&str> {
+        self.labels[level as usize - 1].as_deref()
+    }
+
+    /// Set the label for a level, or `None` to omit the level field for that level.
+    pub fn set(&mut self, level: Level, label: Option<&str>) {
+        self.labels[level as usize - 1] = label.map(String::from);
+    }
+}
+
+/// Formatting options used by [`write_record`].
+///
+/// A [`Logger`](crate::Logger) has its own FormatConfig which is set up by its builder methods.
+/// When using `write_record` directly, create one with [`FormatConfig::new`] and customize it
+/// with the builder methods here.
+#[derive(Debug, Clone)]
+pub struct FormatConfig {
+    pub(crate) colors: LogColors,
+    pub(crate) source_display: SourceDisplayMode,
+    pub(crate) field_order: Vec<Field>,
+    pub(crate) labels: LevelLabels,
+    pub(crate) prefixes: [Option<String>; 5],
+    pub(crate) newline_mode: NewlineMode,
+    pub(crate) show_overhead: bool,
+    pub(crate) max_width: Option<usize>,
+    pub(crate) source_link: Option<String>,
+    pub(crate) time_source: fn() -> String,
+    pub(crate) reset_each_line: bool,
+    pub(crate) target_rewrites: Vec<(String, String)>,
+    pub(crate) brackets_only: bool,
+    pub(crate) dim_location: bool,
+    pub(crate) fixed_width: Option<(usize, usize)>,
+    pub(crate) hostname: Option<String>,
+    pub(crate) program_name: Option<String>,
+    pub(crate) bracket_color: Option<ColorSpec>,
+    pub(crate) message_color: Option<ColorSpec>,
+    pub(crate) record_metadata: bool,
+    #[cfg(feature = "kv")]
+    pub(crate) error_field: Option<String>,
+}
+
+impl Default for FormatConfig {
+    /// The default format, same as [`FormatConfig::new`]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FormatConfig {
+    /// Create a FormatConfig with yall's default formatting.
+    pub fn new() -> FormatConfig {
+        FormatConfig {
+            colors: LogColors::new(),
+            source_display: SourceDisplayMode::FileAndLine,
+            field_order: DEFAULT_FIELD_ORDER.to_vec(),
+            labels: LevelLabels::new(),
+            prefixes: Default::default(),
+            newline_mode: NewlineMode::PassThrough,
+            show_overhead: false,
+            max_width: None,
+            source_link: None,
+            time_source: utc_timestamp,
+            reset_each_line: false,
+            target_rewrites: Vec::new(),
+            brackets_only: false,
+            dim_location: false,
+            fixed_width: None,
+            hostname: None,
+            program_name: None,
+            bracket_color: None,
+            message_color: None,
+            record_metadata: false,
+            #[cfg(feature = "kv")]
+            error_field: None,
+        }
+    }
+
+    /// Set the colors used for each level, see
+    /// [`Logger::color_scheme`](crate::Logger::color_scheme).
+    pub fn colors(mut self, colors: LogColors) -> FormatConfig {
+        self.colors = colors;
+        self
+    }
+
+    /// Show the full filename, see [`Logger::full_filename`](crate::Logger::full_filename).
+    pub fn full_filename(mut self, full: bool) -> FormatConfig {
+        self.source_display =
+            if full { SourceDisplayMode::FullPath } else { SourceDisplayMode::FileAndLine };
+        self
+    }
+
+    /// Set how source locations are shown, see
+    /// [`Logger::with_debug_source_display`](crate::Logger::with_debug_source_display).
+    pub fn source_display(mut self, mode: SourceDisplayMode) -> FormatConfig {
+        self.source_display = mode;
+        self
+    }
+
+    /// Set the field order, see [`Logger::with_field_order`](crate::Logger::with_field_order).
+    pub fn field_order(mut self, fields: impl IntoIterator<Item = Field>) -> FormatConfig {
+        self.field_order = fields.into_iter().collect();
+        self
+    }
+
+    /// Set the level labels, see [`Logger::level_labels`](crate::Logger::level_labels).
+    pub fn level_labels(mut self, labels: LevelLabels) -> FormatConfig {
+        self.labels = labels;
+        self
+    }
+
+    /// Set how newlines in messages are handled, see
+    /// [`Logger::newline_handling`](crate::Logger::newline_handling).
+    pub fn newline_handling(mut self, mode: NewlineMode) -> FormatConfig {
+        self.newline_mode = mode;
+        self
+    }
+
+    /// Append the time spent formatting and writing, see
+    /// [`Logger::show_log_overhead`](crate::Logger::show_log_overhead).
+    pub fn show_log_overhead(mut self, enabled: bool) -> FormatConfig {
+        self.show_overhead = enabled;
+        self
+    }
+
+    /// Word-wrap messages, see
+    /// [`Logger::with_max_line_width`](crate::Logger::with_max_line_width).
+    pub fn max_line_width(mut self, cols: usize) -> FormatConfig {
+        self.max_width = Some(cols);
+        self
+    }
+
+    /// Only color the fields around the message, see
+    /// [`Logger::colorize_brackets`](crate::Logger::colorize_brackets).
+    pub fn colorize_brackets(mut self, enabled: bool) -> FormatConfig {
+        self.brackets_only = enabled;
+        self
+    }
+
+    /// Dim the `[file:line]` field, see [`Logger::dim_location`](crate::Logger::dim_location).
+    pub fn dim_location(mut self, enabled: bool) -> FormatConfig {
+        self.dim_location = enabled;
+        self
+    }
+
+    /// Pad the level and target to fixed-width columns, see
+    /// [`Logger::with_output_width`](crate::Logger::with_output_width).
+    pub fn output_width(mut self, level_width: usize, target_width: usize) -> FormatConfig {
+        self.fixed_width = Some((level_width, target_width));
+        self
+    }
+
+    /// Prefix each line with a hostname, as shown by `Logger::with_hostname` when the `hostname`
+    /// feature is enabled. `None` disables the prefix.
+    pub fn hostname(mut self, hostname: Option<&str>) -> FormatConfig {
+        self.hostname = hostname.map(String::from);
+        self
+    }
+
+    /// Prefix each line with a program name, see
+    /// [`Logger::with_program_name`](crate::Logger::with_program_name).
+    pub fn program_name(mut self, name: &str) -> FormatConfig {
+        self.program_name = Some(String::from(name));
+        self
+    }
+
+    /// Append an error from the record's key-value pairs, as shown by
+    /// `Logger::with_structured_error_field` when the `kv` feature is enabled.
+    #[cfg(feature = "kv")]
+    pub fn structured_error_field(mut self, key: &str) -> FormatConfig {
+        self.error_field = Some(String::from(key));
+        self
+    }
+
+    /// Use one color for all the fields around the message, see
+    /// [`Logger::with_bracket_color`](crate::Logger::with_bracket_color).
+    pub fn bracket_color(mut self, spec: ColorSpec) -> FormatConfig {
+        self.bracket_color = Some(spec);
+        self
+    }
+
+    /// Use one color for the message text, see
+    /// [`Logger::with_message_color`](crate::Logger::with_message_color).
+    pub fn message_color(mut self, spec: ColorSpec) -> FormatConfig {
+        self.message_color = Some(spec);
+        self
+    }
+
+    /// Show all of each record's metadata, see
+    /// [`Logger::with_log_record_metadata`](crate::Logger::with_log_record_metadata).
+    pub fn log_record_metadata(mut self, enabled: bool) -> FormatConfig {
+        self.record_metadata = enabled;
+        self
+    }
+
+    /// Strip a prefix from displayed targets, see
+    /// [`Logger::with_module_prefix_strip`](crate::Logger::with_module_prefix_strip).
+    pub fn module_prefix_strip(mut self, prefix: &str) -> FormatConfig {
+        self.target_rewrites.push((prefix.to_owned(), String::new()));
+        self
+    }
+
+    /// Abbreviate displayed targets, see
+    /// [`Logger::with_target_abbreviation_rules`](crate::Logger::with_target_abbreviation_rules).
+    pub fn target_abbreviations<'a>(
+        mut self,
+        rules: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> FormatConfig {
+        for (prefix, abbrev) in rules {
+            self.target_rewrites.push((prefix.to_owned(), abbrev.to_owned()));
+        }
+        self
+    }
+
+    /// Reset and re-apply colors on each line, see
+    /// [`Logger::with_color_reset_on_each_line`](crate::Logger::with_color_reset_on_each_line).
+    pub fn color_reset_on_each_line(mut self, enabled: bool) -> FormatConfig {
+        self.reset_each_line = enabled;
+        self
+    }
+
+    /// Use a custom timestamp function, see
+    /// [`Logger::with_custom_time_source`](crate::Logger::with_custom_time_source).
+    pub fn time_source(mut self, f: fn() -> String) -> FormatConfig {
+        self.time_source = f;
+        if !self.field_order.contains(&Field::Timestamp) {
+            self.field_order.insert(0, Field::Timestamp);
+        }
+        self
+    }
+
+    /// Make source locations into hyperlinks, see
+    /// [`Logger::with_source_link`](crate::Logger::with_source_link).
+    pub fn source_link(mut self, base_url: &str) -> FormatConfig {
+        self.source_link = Some(format!("{}{{file}}#L{{line}}", base_url));
+        self
+    }
+
+    /// Make source locations into hyperlinks with a custom URL format, see
+    /// [`Logger::with_ansi_hyperlink_format`](crate::Logger::with_ansi_hyperlink_format).
+    pub fn hyperlink_format(mut self, format: &str) -> FormatConfig {
+        self.source_link = Some(String::from(format));
+        self
+    }
+
+    /// Replace the level field with custom text, see
+    /// [`Logger::progress_prefix`](crate::Logger::progress_prefix).
+    pub fn progress_prefix(mut self, patterns: &[(Level, &str)]) -> FormatConfig {
+        for (level, prefix) in patterns.iter() {
+            self.prefixes[*level as usize - 1] = Some(String::from(*prefix));
+        }
+        self
+    }
+}
+
+/// Format a log record the same way as [`Logger`](crate::Logger) and write it to `out`, including
+/// a trailing newline. Colors are applied if `out` supports them.
+///
+/// No level filtering is done here, the record is always written.
+pub fn write_record<W: WriteColor + ?Sized>(
+    r: &Record,
+    config: &FormatConfig,
+    out: &mut W,
+) -> io::Result<()> {
+    let level = r.level();
+    let debug_or_trace = level == Level::Debug || level == Level::Trace;
+    let start = if config.show_overhead && debug_or_trace { Some(Instant::now()) } else { None };
+
+    // strip "src/" prefix and ".rs" suffix
+    let mut filename = r.file().unwrap_or("?");
+    if config.source_display != SourceDisplayMode::FullPath && debug_or_trace {
+        // we could use str::strip_{prefix,suffix} here, but they're not stable until
+        // rust 1.45 and return Options which is kinda clunky.
+        if filename.starts_with("src/") {
+            filename = &filename[4..];
+        }
+        if filename.ends_with(".rs") {
+            filename = &filename[..(filename.len() - 3)];
+        }
+    }
+
+    // the current column, so that wrapped lines can be aligned with the start of the message
+    let mut col = 0;
+    // line prefixes are written without color, before any of the fields
+    for prefix in config.hostname.iter().chain(config.program_name.iter()) {
+        write!(out, "[{}] ", prefix)?;
+        col += display_width(prefix) + 3;
+    }
+
+    // the color currently set on out, None for plain text
+    let mut current = None;
+
+    // Bracketed fields are written right next to each other, everything else gets a space.
+    // prev_bracketed is None until the first field has been written.
+    let mut prev_bracketed: Option<bool> = None;
+    let prefix = config.prefixes[level as usize - 1].as_deref();
+    for field in config.field_order.iter() {
+        // a custom prefix replaces the entire level field including brackets, so it gets spaces
+        // around it like a timestamp.
+        let bracketed = match field {
+            Field::Timestamp | Field::Message => false,
+            Field::Level => prefix.is_none() || config.record_metadata,
+            _ => true,
+        };
+        if !field_applies(config, *field, level) {
+            continue;
+        }
+        // only change colors when needed, e.g. when switching between the message and the
+        // other fields with colorize_brackets
+        let want = field_color(config, *field, level);
+        if want != current {
+            match want {
+                Some(spec) => out.set_color(spec)?,
+                None => out.reset()?,
+            }
+            current = want;
+        }
+        match prev_bracketed {
+            Some(prev) if !(prev && bracketed) => {
+                out.write_all(b" ")?;
+                col += 1;
+            }
+            _ => (),
+        }
+        let text = match field {
+            Field::Timestamp => (config.time_source)(),
+            Field::Level if config.record_metadata => record_metadata(r, config),
+            Field::Level => match (prefix, config.labels.get(level)) {
+                (Some(p), _) => String::from(p),
+                (None, Some(label)) => format!("[{}]", label),
+                // only reachable with a fixed width, where Info still gets an empty column
+                (None, None) => String::new(),
+            },
+            Field::Target => format!("[{}]", display_target(config, r.target())),
+            Field::Location => {
+                let line = r.line().unwrap_or(0);
+                match config.source_display {
+                    SourceDisplayMode::FileOnly => format!("[{}]", filename),
+                    SourceDisplayMode::LineOnly => format!("[line {}]", line),
+                    SourceDisplayMode::ModulePath => {
+                        format!("[{}:{}]", r.module_path().unwrap_or("?"), line)
+                    }
+                    SourceDisplayMode::ModuleAndFile => {
+                        format!("[{} {}:{}]", r.module_path().unwrap_or("?"), filename, line)
+                    }
+                    _ => format!("[{}:{}]", filename, line),
+                }
+            }
+            Field::Message => {
+                write_message(r, config, out, col)?;
+                prev_bracketed = Some(bracketed);
+                continue;
+            }
+        };
+        let text = match (field, config.fixed_width) {
+            (Field::Level, Some((w, _))) => fit_width(text, w),
+            (Field::Target, Some((_, w))) => fit_width(text, w),
+            _ => text,
+        };
+        let dimmed = config.dim_location && *field == Field::Location;
+        if dimmed {
+            let mut spec = current.cloned().unwrap_or_default();
+            out.set_color(spec.set_dimmed(true))?;
+        }
+        match (field, &config.source_link) {
+            // OSC 8 hyperlinks are only written to terminals, which is approximated by whether
+            // color is supported, and aren't counted toward the column.
+            (Field::Location, Some(link)) if out.supports_color() => {
+                let url = link
+                    .replace("{file}", r.file().unwrap_or("?"))
+                    .replace("{line}", &r.line().unwrap_or(0).to_string());
+                write!(out, "\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)?;
+            }
+            _ => out.write_all(text.as_bytes())?,
+        }
+        if dimmed {
+            match current {
+                Some(spec) => out.set_color(spec)?,
+                None => out.reset()?,
+            }
+        }
+        col += display_width(&text);
+        prev_bracketed = Some(bracketed);
+    }
+    #[cfg(feature = "kv")]
+    write_error_field(r, config, out)?;
+    if let Some(start) = start {
+        write!(out, " [log={}µs]", start.elapsed().as_micros())?;
+    }
+    writeln!(out)?;
+    out.reset()?;
+    Ok(())
+}
+
+/// Write the configured error key-value pair and its source chain as ` key=top: cause: root`.
+#[cfg(feature = "kv")]
+fn write_error_field<W: WriteColor + ?Sized>(
+    r: &Record,
+    config: &FormatConfig,
+    out: &mut W,
+) -> io::Result<()> {
+    let key = match &config.error_field {
+        Some(key) => key,
+        None => return Ok(()),
+    };
+    let value = match r.key_values().get(log::kv::Key::from_str(key)) {
+        Some(value) => value,
+        None => return Ok(()),
+    };
+    match value.to_borrowed_error() {
+        Some(err) => {
+            write!(out, " {}={}", key, err)?;
+            let mut source = err.source();
+            while let Some(cause) = source {
+                write!(out, ": {}", cause)?;
+                source = cause.source();
+            }
+            Ok(())
+        }
+        // not an error, but still show it rather than silently dropping it
+        None => write!(out, " {}={}", key, value),
+    }
+}
+
+/// Apply the first matching target prefix rewrite, if any.
+fn display_target<'a>(config: &FormatConfig, target: &'a str) -> Cow<'a, str> {
+    for (prefix, replacement) in config.target_rewrites.iter() {
+        if let Some(rest) = target.strip_prefix(prefix.as_str()) {
+            if replacement.is_empty() {
+                return Cow::Borrowed(rest);
+            }
+            return Cow::Owned(format!("{}{}", replacement, rest));
+        }
+    }
+    Cow::Borrowed(target)
+}
+
+/// Write a record's message, applying the configured newline handling and line wrapping. `col` is
+/// the column where the message starts.
+fn write_message<W: WriteColor + ?Sized>(
+    r: &Record,
+    config: &FormatConfig,
+    out: &mut W,
+    col: usize,
+) -> io::Result<()> {
+    if config.newline_mode == NewlineMode::PassThrough
+        && config.max_width.is_none()
+        && !config.reset_each_line
+    {
+        return write!(out, "{}", r.args());
+    }
+
+    let color =
+        if config.reset_each_line { field_color(config, Field::Message, r.level()) } else { None };
+    let msg = r.args().to_string();
+    for (i, line) in msg.split('\n').enumerate() {
+        if i > 0 {
+            match config.newline_mode {
+                NewlineMode::PassThrough => write_newline(out, 0, color)?,
+                NewlineMode::Indent(n) => write_newline(out, n, color)?,
+                NewlineMode::Replace(c) => write!(out, "{}", c)?,
+                NewlineMode::Escape => out.write_all(b"\\n")?,
+            }
+        }
+        match config.max_width {
+            Some(width) => write_wrapped(line, width, col, color, out)?,
+            None => out.write_all(line.as_bytes())?,
+        }
+    }
+    Ok(())
+}
+
+/// Write a newline followed by `indent` spaces. If `color` is set, reset the color before the
+/// newline and set it again afterwards, so that each line has its color applied separately.
+fn write_newline<W: WriteColor + ?Sized>(
+    out: &mut W,
+    indent: usize,
+    color: Option<&ColorSpec>,
+) -> io::Result<()> {
+    match color {
+        Some(spec) => {
+            out.reset()?;
+            write!(out, "\n{:1$}", "", indent)?;
+            out.set_color(spec)
+        }
+        None => write!(out, "\n{:1$}", "", indent),
+    }
+}
+
+/// Write a line of text word-wrapped so that it fits within `width` columns, given that it starts
+/// at column `col`. Continuation lines are indented to `col`. Leading whitespace is preserved, but
+/// runs of spaces between words are collapsed. Words longer than the available width are written
+/// on their own line without being broken. `color` is passed along to [`write_newline`].
+fn write_wrapped<W: WriteColor + ?Sized>(
+    line: &str,
+    width: usize,
+    col: usize,
+    color: Option<&ColorSpec>,
+    out: &mut W,
+) -> io::Result<()> {
+    // if the prefix takes up nearly the whole width, wrapping would be useless, so allow at least
+    // a reasonable amount of space for the text.
+    let avail = width.saturating_sub(col).max(20);
+
+    let trimmed = line.trim_start();
+    let leading = &line[..(line.len() - trimmed.len())];
+    out.write_all(leading.as_bytes())?;
+    let mut line_width = display_width(leading);
+
+    for (i, word) in trimmed.split_whitespace().enumerate() {
+        let word_width = display_width(word);
+        if i > 0 && line_width + 1 + word_width > avail {
+            write_newline(out, col, color)?;
+            line_width = 0;
+        } else if i > 0 {
+            out.write_all(b" ")?;
+            line_width += 1;
+        }
+        out.write_all(word.as_bytes())?;
+        line_width += word_width;
+    }
+    Ok(())
+}
+
+/// The number of columns a string takes up on a terminal, not counting ANSI escape sequences.
+/// This counts chars rather than handling wide characters, which is good enough for log messages.
+fn display_width(s: &str) -> usize {
+    let mut width = 0;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // CSI sequences are ESC [ params final-byte, where the final byte is in '@'..='~'.
+            // For other escapes, just skip the following character.
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
+
+/// Whether a field should be written for a record of the given level. Levels without a label or
+/// custom prefix (Info by default) have no level field, and only Debug and Trace messages show
+/// their source location.
+fn field_applies(config: &FormatConfig, field: Field, level: Level) -> bool {
+    match field {
+        Field::Level => {
+            config.labels.get(level).is_some()
+                || config.prefixes[level as usize - 1].is_some()
+                || config.fixed_width.is_some()
+                || config.record_metadata
+        }
+        // the location is already part of the metadata header
+        Field::Location => {
+            (level == Level::Debug || level == Level::Trace)
+                && config.source_display != SourceDisplayMode::None
+                && !config.record_metadata
+        }
+        _ => true,
+    }
+}
+
+/// The level field with all of the record's metadata, like
+/// `[ERROR target=myapp::db file=src/db.rs:42 module=myapp::db]`.
+fn record_metadata(r: &Record, config: &FormatConfig) -> String {
+    let level = r.level();
+    let mut text = format!("[{}", config.labels.get(level).unwrap_or(level.as_str()));
+    if !r.target().is_empty() {
+        text += &format!(" target={}", display_target(config, r.target()));
+    }
+    match (r.file(), r.line()) {
+        (Some(file), Some(line)) => text += &format!(" file={}:{}", file, line),
+        (Some(file), None) => text += &format!(" file={}", file),
+        _ => (),
+    }
+    if let Some(module) = r.module_path() {
+        text += &format!(" module={}", module);
+    }
+    text.push(']');
+    text
+}
+
+/// Pad `text` with spaces to `width` characters, or truncate it with `…` if it's longer. A
+/// bracketed field keeps its brackets, only the text inside them is truncated.
+fn fit_width(text: String, width: usize) -> String {
+    if text.chars().count() <= width {
+        return format!("{:<width$}", text, width = width);
+    }
+    match text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+        Some(inner) if width >= 3 => format!("[{}]", truncate(inner, width - 2)),
+        _ => truncate(&text, width),
+    }
+}
+
+/// Cut `text` down to `width` characters, with `…` as the last one.
+fn truncate(text: &str, width: usize) -> String {
+    let mut fitted: String = text.chars().take(width.saturating_sub(1)).collect();
+    fitted.push('…');
+    fitted
+}
+
+/// The color to use for a field, or None for no color. The message and the other fields can be
+/// colored separately, otherwise everything gets the level's color.
+fn field_color(config: &FormatConfig, field: Field, level: Level) -> Option<&ColorSpec> {
+    let level_color = config.colors.get(level);
+    if field == Field::Message {
+        match &config.message_color {
+            Some(spec) => Some(spec),
+            None if config.brackets_only => None,
+            None => Some(level_color),
+        }
+    } else {
+        Some(config.bracket_color.as_ref().unwrap_or(level_color))
+    }
+}
+
+/// Replace each non-ASCII character with a `\uXXXX` escape, e.g. `é` becomes `\u00E9`.
+/// Characters outside the BMP are written as a UTF-16 surrogate pair like in JSON.
+pub(crate) fn escape_non_ascii(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii() {
+            out.push(c);
+        } else {
+            for unit in c.encode_utf16(&mut [0; 2]) {
+                out += &format!("\\u{:04X}", unit);
+            }
+        }
+    }
+    out
+}
+
+/// Build a separator line of `width` columns with `text` centered in it, e.g.
+/// `────── phase 2 ──────`, using `dash` for the line. `dash` is assumed to be one column wide.
+pub(crate) fn separator_line(text: &str, width: usize, dash: &str) -> String {
+    let text_len = if text.is_empty() { 0 } else { text.chars().count() + 2 };
+    // always have at least a couple dashes on each side, even if that overflows the width
+    let dashes = width.saturating_sub(text_len).max(4);
+    let left = dashes / 2;
+
+    let mut line = dash.repeat(left);
+    if !text.is_empty() {
+        line.push(' ');
+        line.push_str(text);
+        line.push(' ');
+    }
+    line.push_str(&dash.repeat(dashes - left));
+    line
+}
+
+/// Format the current system time as a UTC RFC 3339 timestamp with one-second precision. This is
+/// done by hand to avoid pulling in chrono or time for a single format.
+pub(crate) fn utc_timestamp() -> String {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    format_timestamp(secs)
+}
+
+/// Format `secs` since the Unix epoch as a UTC RFC 3339 timestamp.
+fn format_timestamp(secs: u64) -> String {
+    let (days, rem) = (secs / 86400, secs % 86400);
+    let (hour, min, sec) = (rem / 3600, (rem % 3600) / 60, rem % 60);
+
+    // Convert days since 1970-01-01 to a civil date, using Howard Hinnant's days_from_civil
+    // algorithm in reverse. See http://howardhinnant.github.io/date_algorithms.html
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, hour, min, sec)
+}
+
+#[cfg(test)]
+mod tests {
+    use termcolor::NoColor;
+
+    use super::*;
+
+    #[test]
+    fn fit_width_pads() {
+        assert_eq!(fit_width(String::from("[WARN]"), 8), "[WARN]  ");
+        assert_eq!(fit_width(String::new(), 3), "   ");
+    }
+
+    #[test]
+    fn fit_width_truncates() {
+        assert_eq!(fit_width(String::from("abcdef"), 4), "abc…");
+        assert_eq!(fit_width(String::from("abc"), 3), "abc");
+    }
+
+    #[test]
+    fn fit_width_keeps_brackets() {
+        assert_eq!(fit_width(String::from("[a::very::long::target]"), 10), "[a::very…]");
+        assert_eq!(fit_width(String::from("[CRITICAL]"), 6), "[CRI…]");
+        // too narrow for the brackets and any text
+        assert_eq!(fit_width(String::from("[ERROR]"), 2), "[…");
+    }
+
+    fn wrapped(line: &str, width: usize, col: usize) -> String {
+        let mut out = NoColor::new(Vec::new());
+        write_wrapped(line, width, col, None, &mut out).unwrap();
+        String::from_utf8(out.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn wrap_fits() {
+        assert_eq!(wrapped("short message", 40, 0), "short message");
+        assert_eq!(wrapped("  indented   and  spaced", 40, 0), "  indented and spaced");
+    }
+
+    #[test]
+    fn wrap_indents_continuation_lines() {
+        assert_eq!(
+            wrapped("aaaa bbbb cccc dddd eeee ffff gggg", 30, 5),
+            "aaaa bbbb cccc dddd eeee\n     ffff gggg"
+        );
+    }
+
+    #[test]
+    fn wrap_long_words() {
+        let long = "x".repeat(30);
+        assert_eq!(wrapped(&format!("a {} b", long), 25, 0), format!("a\n{}\nb", long));
+    }
+
+    #[test]
+    fn wrap_min_width() {
+        // the prefix is wider than the limit, so 20 columns are allowed anyway
+        let expected = format!("aaaa bbbb cccc dddd\n{}eeee", " ".repeat(30));
+        assert_eq!(wrapped("aaaa bbbb cccc dddd eeee", 10, 30), expected);
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_timestamp(1_709_251_199), "2024-02-29T23:59:59Z");
+        assert_eq!(format_timestamp(1_735_689_599), "2024-12-31T23:59:59Z");
+        assert_eq!(format_timestamp(4_107_542_400), "2100-03-01T00:00:00Z");
+    }
+
+    #[test]
+    fn display_width_skips_escapes() {
+        assert_eq!(display_width("plain"), 5);
+        assert_eq!(display_width("\x1b[1;31mred\x1b[0m"), 3);
+        assert_eq!(display_width("é…"), 2);
+    }
+}
@@ -0,0 +1,75 @@
+// Copyright (c) 2020 Allen Wild
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Unix domain socket output, for
+//! [`Logger::with_output_to_unix_socket`](crate::Logger::with_output_to_unix_socket).
+
+use std::io::{self, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::Duration;
+
+/// How long the background thread waits between connection attempts.
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+type Connection = Mutex<Option<UnixStream>>;
+
+/// A writer which sends log lines to a Unix stream socket. While it's not connected, a background
+/// thread keeps trying to connect and writes are discarded.
+pub(crate) struct UnixSocketWriter {
+    path: PathBuf,
+    stream: Arc<Connection>,
+}
+
+impl UnixSocketWriter {
+    pub(crate) fn connect(path: PathBuf) -> UnixSocketWriter {
+        let stream = Arc::new(Mutex::new(UnixStream::connect(&path).ok()));
+        let writer = UnixSocketWriter { path, stream };
+        if writer.stream.lock().unwrap().is_none() {
+            writer.spawn_reconnect();
+        }
+        writer
+    }
+
+    /// Start a thread which connects in the background. It only holds a weak reference to the
+    /// connection, so it exits once the Logger is dropped.
+    fn spawn_reconnect(&self) {
+        let path = self.path.clone();
+        let weak: Weak<Connection> = Arc::downgrade(&self.stream);
+        thread::spawn(move || loop {
+            thread::sleep(RETRY_INTERVAL);
+            let conn = match weak.upgrade() {
+                Some(conn) => conn,
+                None => return,
+            };
+            if let Ok(stream) = UnixStream::connect(&path) {
+                *conn.lock().unwrap_or_else(|e| e.into_inner()) = Some(stream);
+                return;
+            }
+        });
+    }
+}
+
+impl Write for UnixSocketWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut conn = self.stream.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(stream) = conn.as_mut() {
+            if stream.write_all(buf).is_err() {
+                // the receiver went away, wait for it to come back
+                *conn = None;
+                drop(conn);
+                self.spawn_reconnect();
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.stream.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+            Some(stream) => stream.flush(),
+            None => Ok(()),
+        }
+    }
+}
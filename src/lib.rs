@@ -15,16 +15,115 @@
 //!   * Debug and Trace levels show the filename and line number.
 //!   * Minimal dependencies
 //!   * Configured with code rather than environment variables
+//!
+//! ## Optional features
+//!
+//!   * `clap`: adds the `cli` module with a `clap::Args` struct for yall's command-line options,
+//!     and `Logger::with_level_from_clap_matches` for clap's builder API.
+//!   * `compress`: allows gzipping rotated log files, see [`RotateConfig::compress_old`].
+//!   * `encoding`: enables `Logger::with_output_encoding` for writing Latin-1 or Windows-1252
+//!     rather than UTF-8.
+//!   * `hostname`: enables `Logger::with_hostname` to show the machine's hostname on each line.
+//!   * `kv`: enables `Logger::with_structured_error_field` to show errors attached to log records
+//!     as key-value pairs.
+//!   * `net`: enables `Logger::with_output_to_udp` and `Logger::with_output_to_tcp` for sending
+//!     log messages over the network.
+//!   * `regex`: enables `Logger::with_target_filter_regex` to set levels for targets matching a
+//!     regular expression.
+//!   * `sentry`: enables `Logger::with_sentry_integration` to report errors and warnings to
+//!     [Sentry](https://sentry.io).
+//!   * `terminfo`: enables `Logger::with_color_support_detection_via_terminfo` to check the
+//!     terminal's color support with terminfo.
+//!   * `tokio`: enables `LogWorker::run_async` in the [`async_logger`] module.
+//!   * `tracing`: enables `Logger::forward_to_tracing` to send log records to a `tracing`
+//!     subscriber instead of stderr.
 
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::error::Error;
 use std::fmt;
+use std::fs::{self, File, OpenOptions};
 use std::io::{self, IsTerminal, Write};
-use std::sync::Mutex;
+use std::mem;
+#[cfg(unix)]
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "net")]
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Once, OnceLock, PoisonError, TryLockError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::{Log, Metadata, Record, SetLoggerError};
+use termcolor::{
+    Buffer, BufferedStandardStream, Color, ColorChoice, ColorSpec, NoColor, StandardStream,
+    WriteColor,
+};
+
+#[doc(no_inline)]
+pub use log::{Level, LevelFilter};
 
-use log::{Level, Log, Metadata, Record, SetLoggerError};
-use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+/// Re-export of the termcolor crate, which yall uses for colors and output streams.
+pub use termcolor;
 
+pub mod async_logger;
+#[cfg(feature = "clap")]
+pub mod cli;
+pub mod compat;
+mod config;
+pub mod context;
+#[cfg(feature = "encoding")]
+mod encoding;
+pub mod env_filter;
+mod format;
+#[cfg(unix)]
+mod ipc;
+#[cfg(feature = "net")]
+mod net;
+pub mod ring;
+pub mod rotate;
+pub mod schemes;
+#[cfg(feature = "terminfo")]
+mod terminfo;
+#[cfg(feature = "encoding")]
+pub use encoding::OutputEncoding;
+#[doc(no_inline)]
+pub use env_filter::EnvFilter;
+#[doc(no_inline)]
+pub use ring::RingBuffer;
+#[doc(no_inline)]
+pub use rotate::RotateConfig;
 #[doc(no_inline)]
-pub use log::LevelFilter;
+pub use schemes::ColorScheme;
+
+use async_logger::BackpressurePolicy;
+use format::{escape_non_ascii, separator_line};
+pub use format::{
+    write_record, Field, FormatConfig, LevelLabels, LocationDisplay, LogColors, NewlineMode,
+    SourceDisplayMode,
+};
+
+/// The version of yall, e.g. for logging in a startup banner.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// The major version of yall.
+pub const VERSION_MAJOR: u32 = parse_version(env!("CARGO_PKG_VERSION_MAJOR"));
+/// The minor version of yall.
+pub const VERSION_MINOR: u32 = parse_version(env!("CARGO_PKG_VERSION_MINOR"));
+/// The patch version of yall.
+pub const VERSION_PATCH: u32 = parse_version(env!("CARGO_PKG_VERSION_PATCH"));
+
+/// Parse a version number at compile time, since str::parse isn't const.
+const fn parse_version(s: &str) -> u32 {
+    let bytes = s.as_bytes();
+    let mut val = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        val = val * 10 + (bytes[i] - b'0') as u32;
+        i += 1;
+    }
+    val
+}
 
 /// Re-exports of the error, warn, info, debug, and trace macros in the log crate.
 ///
@@ -35,7 +134,8 @@ pub mod log_macros {
 }
 
 /// Whether to enable colored output, the usual suspects.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
 pub enum ColorMode {
     /// Enable color automatically if stderr is a tty, plus the `TERM` and `NO_COLOR` environment
     /// variable checks done by `termcolor`'s [`ColorChoice::Auto`] variant.
@@ -48,8 +148,8 @@ pub enum ColorMode {
 
 impl ColorMode {
     /// Internal function to map ColorMode to a termcolor::ColorChoice that Logger uses internally.
-    /// This is mainly to keep termcolor out of yall's API.
-    fn to_color_choice(&self) -> ColorChoice {
+    /// This is also available publicly through the `From` impl.
+    fn to_color_choice(self) -> ColorChoice {
         match self {
             ColorMode::Auto => {
                 if io::stderr().is_terminal() {
@@ -65,6 +165,14 @@ impl ColorMode {
     }
 }
 
+/// Convert to the termcolor equivalent, for creating other termcolor streams which follow the same
+/// color setting as yall, with the same tty detection as `Auto` does for stderr.
+impl From<ColorMode> for ColorChoice {
+    fn from(c: ColorMode) -> Self {
+        c.to_color_choice()
+    }
+}
+
 impl Default for ColorMode {
     /// The default ColorMode is `Auto`
     fn default() -> Self {
@@ -72,52 +180,53 @@ impl Default for ColorMode {
     }
 }
 
-#[derive(Debug)]
-struct LogColors {
-    error: ColorSpec,
-    warn: ColorSpec,
-    info: ColorSpec,
-    debug: ColorSpec,
-    trace: ColorSpec,
-}
-
-impl LogColors {
-    pub fn new() -> Self {
-        // The set_* functions return &mut, so we need to_owned() to convert back to an actual
-        // value. Since ColorSpec doesn't implement Copy, we can't just dereference.
-        let error = ColorSpec::new().set_fg(Some(Color::Red)).set_bold(true).to_owned();
-        let warn = ColorSpec::new().set_fg(Some(Color::Yellow)).set_bold(true).to_owned();
-        let info = ColorSpec::new();
-        let debug = ColorSpec::new().set_fg(Some(Color::Cyan)).to_owned();
-        let trace = ColorSpec::new().set_fg(Some(Color::Blue)).to_owned();
-
-        Self { error, warn, info, debug, trace }
-    }
-
-    pub fn get(&self, l: Level) -> &ColorSpec {
-        match l {
-            Level::Error => &self.error,
-            Level::Warn => &self.warn,
-            Level::Info => &self.info,
-            Level::Debug => &self.debug,
-            Level::Trace => &self.trace,
-        }
-    }
+/// The kind of lock used to serialize access to a Logger's output, see
+/// [`Logger::with_output_lock_type`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum OutputLock {
+    /// A [`Mutex`], where every access is exclusive.
+    #[default]
+    Mutex,
+    /// An [`RwLock`](std::sync::RwLock), which would let read-only operations run concurrently.
+    /// This isn't implemented yet and is rejected by [`Logger::build`].
+    RwLock,
 }
 
-/// Internal extension trait for working with log::LevelFilter as an integer. Since LevelFilter is
-/// Copy, all these methods take self by value to avoid unnecessary pointers.
-trait LevelFilterExt {
+/// Extension trait for working with log::LevelFilter as an integer, where 0 is Off and 5 is Trace.
+/// Since LevelFilter is Copy, all these methods take self by value to avoid unnecessary pointers.
+///
+/// This trait is sealed and only implemented for LevelFilter, so that methods can be added to it
+/// without breaking other crates.
+pub trait LevelFilterExt: Copy + private::Sealed {
+    /// Convert from an integer, values above 5 are clamped to Trace.
     fn from_int(val: u8) -> Self;
+    /// Convert to an integer.
     fn to_int(self) -> u8;
+    /// Increase verbosity by `change` levels, saturating at Trace.
     fn add(self, change: u8) -> Self;
+    /// Decrease verbosity by `change` levels, saturating at Off.
     fn sub(self, change: u8) -> Self;
+    /// Increase (if positive) or decrease (if negative) verbosity.
+    fn adjust(self, delta: i8) -> Self;
+    /// Whether this level enables at least everything that `min` does, i.e. `self >= min`. For
+    /// example, `Logger::new().level().is_at_least(LevelFilter::Debug)` is true if Debug messages
+    /// are shown.
+    fn is_at_least(self, min: Self) -> bool;
+    /// The lowercase name of this level, e.g. `"info"`. For the uppercase name, use
+    /// `LevelFilter`'s own `as_str`.
+    fn as_lower_str(self) -> &'static str;
 }
 
 // LevelFilter is Copy and repr(usize) and the match blocks here are the same as LevelFilter's
 // discriminant order, so they compile down to almost nothing. from_int is one branch for val>5,
 // to_int is a single move that basically gets inlined into a nop.
 // Not that this is hot code anyway...
+mod private {
+    pub trait Sealed {}
+    impl Sealed for log::LevelFilter {}
+}
+
 impl LevelFilterExt for LevelFilter {
     fn from_int(val: u8) -> Self {
         match val {
@@ -148,6 +257,387 @@ impl LevelFilterExt for LevelFilter {
     fn sub(self, change: u8) -> Self {
         Self::from_int(self.to_int().saturating_sub(change))
     }
+
+    fn adjust(self, delta: i8) -> Self {
+        if delta >= 0 {
+            self.add(delta as u8)
+        } else {
+            self.sub(delta.unsigned_abs())
+        }
+    }
+
+    fn is_at_least(self, min: Self) -> bool {
+        self.to_int() >= min.to_int()
+    }
+
+    fn as_lower_str(self) -> &'static str {
+        match self {
+            LevelFilter::Off => "off",
+            LevelFilter::Error => "error",
+            LevelFilter::Warn => "warn",
+            LevelFilter::Info => "info",
+            LevelFilter::Debug => "debug",
+            LevelFilter::Trace => "trace",
+        }
+    }
+}
+
+/// Errors from validating a [`Logger`] configuration with [`Logger::build`] or registering it.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// A module name passed to [`Logger::module_level`] is empty.
+    InvalidModulePattern(String),
+    /// Some options were used together in a way that doesn't make sense.
+    ConflictingOptions(String),
+    /// An option isn't supported by this version of yall.
+    Unsupported(String),
+    /// An output couldn't be opened.
+    IoError(io::Error),
+    /// Another logger has already been registered with the [`log`](::log) crate.
+    SetLogger(SetLoggerError),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::InvalidModulePattern(m) => write!(f, "invalid module pattern '{}'", m),
+            ConfigError::ConflictingOptions(msg) => write!(f, "conflicting options: {}", msg),
+            ConfigError::Unsupported(msg) => write!(f, "unsupported option: {}", msg),
+            ConfigError::IoError(e) => write!(f, "failed to open log output: {}", e),
+            ConfigError::SetLogger(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::IoError(e) => Some(e),
+            ConfigError::SetLogger(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(e: io::Error) -> Self {
+        ConfigError::IoError(e)
+    }
+}
+
+impl From<SetLoggerError> for ConfigError {
+    fn from(e: SetLoggerError) -> Self {
+        ConfigError::SetLogger(e)
+    }
+}
+
+/// Why a record would or wouldn't be logged, as returned by [`Logger::would_filter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FilterDecision {
+    /// The record would be logged.
+    Pass,
+    /// The record is more verbose than the global level.
+    FilteredByGlobalLevel,
+    /// The record is more verbose than the level set for this module with
+    /// [`Logger::module_level`].
+    FilteredByModuleOverride(String),
+    /// The record is more verbose than the level set for a regex matching its target with
+    /// `Logger::with_target_filter_regex`.
+    FilteredByTargetRegex(String),
+}
+
+/// A wrapper for displaying a [`LevelFilter`] along with its verbosity number, as used by
+/// [`Logger::with_verbosity`]. For example, `DisplayLevel(LevelFilter::Info)` displays as
+/// `Info(3)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayLevel(pub LevelFilter);
+
+impl fmt::Display for DisplayLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // LevelFilter's Debug is the variant name, while Display is all uppercase
+        write!(f, "{:?}({})", self.0, self.0.to_int())
+    }
+}
+
+/// Create a disabled [`Logger`] which discards its output, same as [`Logger::sink`].
+pub fn sink() -> Logger {
+    Logger::sink()
+}
+
+/// Read a verbosity number from the environment variable `var_name`, returning `default` if the
+/// variable isn't set or isn't a valid `u8`. Pairs with [`Logger::with_verbosity`], e.g.
+/// `Logger::with_verbosity(verbosity_from_env_or("MYAPP_VERBOSE", 3))`.
+pub fn verbosity_from_env_or(var_name: &str, default: u8) -> u8 {
+    std::env::var(var_name).ok().and_then(|v| v.trim().parse().ok()).unwrap_or(default)
+}
+
+/// Number of records suppressed at each level, see [`Logger::count_filtered_records`].
+static SUPPRESSED: [AtomicU64; 5] = {
+    // only used as an array repeat operand, each element is a separate atomic
+    #[allow(clippy::declare_interior_mutable_const)]
+    const ZERO: AtomicU64 = AtomicU64::new(0);
+    [ZERO; 5]
+};
+
+/// The Logger registered by [`Logger::try_init`], if any, for the [`context`] functions.
+static REGISTERED: OnceLock<&'static Logger> = OnceLock::new();
+
+/// The Logger registered as the global logger by [`Logger::init`] or [`Logger::try_init`].
+pub(crate) fn registered_logger() -> Option<&'static Logger> {
+    REGISTERED.get().copied()
+}
+
+/// The number of records at `level` which were filtered out, if the global logger was created
+/// with [`Logger::count_filtered_records`]. Always 0 otherwise.
+pub fn suppressed_count(level: Level) -> u64 {
+    SUPPRESSED[level as usize - 1].load(Ordering::Relaxed)
+}
+
+/// Print a summary of the number of suppressed records at each level to stderr, see
+/// [`Logger::count_filtered_records`].
+pub fn print_suppression_summary() {
+    eprintln!("{}", suppression_summary());
+}
+
+fn suppression_summary() -> String {
+    let mut summary = String::from("yall: suppressed records:");
+    for level in Level::iter() {
+        summary += &format!(" {}={}", level.as_str().to_lowercase(), suppressed_count(level));
+    }
+    summary
+}
+
+/// A function called with the old and new levels when the level changes.
+type LevelChangeHook = fn(LevelFilter, LevelFilter);
+
+/// A level, a record count, and a function to call when that many records have been logged at
+/// that level.
+type Watermark = (Level, u64, fn(u64));
+
+/// State shared between a [`Logger`] and the application after the logger has been registered,
+/// returned by [`Logger::try_init_and_return`].
+///
+/// This holds the current log level and the number of records logged at each level, so that an
+/// application can check things like "did we log any errors?" before exiting.
+#[derive(Debug)]
+pub struct LogState {
+    level: AtomicU8,
+    override_max: AtomicU8,
+    counts: [AtomicU64; 5],
+    dropped: AtomicU64,
+    on_level_change: Mutex<Option<LevelChangeHook>>,
+}
+
+impl LogState {
+    fn new(level: LevelFilter) -> LogState {
+        LogState {
+            level: AtomicU8::new(level.to_int()),
+            override_max: AtomicU8::new(0),
+            counts: Default::default(),
+            dropped: AtomicU64::new(0),
+            on_level_change: Mutex::new(None),
+        }
+    }
+
+    /// The current log level.
+    pub fn level(&self) -> LevelFilter {
+        LevelFilter::from_int(self.level.load(Ordering::Relaxed))
+    }
+
+    /// Change the global log level at runtime. This also updates the [`log`](::log) crate's max
+    /// level, so it should only be used with a logger that has been registered. Per-module
+    /// levels set with [`Logger::module_level`] aren't affected.
+    ///
+    /// If the level changed, the hook set by [`Logger::on_level_change`] is called.
+    pub fn set_level(&self, level: LevelFilter) {
+        let old = LevelFilter::from_int(self.level.swap(level.to_int(), Ordering::Relaxed));
+        log::set_max_level(self.max_level());
+
+        // copy the hook out so that the lock isn't held while it runs
+        let hook = *self.on_level_change.lock().unwrap();
+        if let Some(f) = hook {
+            if old != level {
+                f(old, level);
+            }
+        }
+    }
+
+    /// The most verbose level enabled by either the global level or an override like a module
+    /// level, which is what the log crate's max level needs to be set to.
+    fn max_level(&self) -> LevelFilter {
+        self.level().max(LevelFilter::from_int(self.override_max.load(Ordering::Relaxed)))
+    }
+
+    /// Store a new level without updating the log crate's max level.
+    fn store_level(&self, level: LevelFilter) {
+        self.level.store(level.to_int(), Ordering::Relaxed);
+    }
+
+    /// The number of records which have been logged at the given level. Records which were
+    /// filtered out aren't counted.
+    pub fn count(&self, level: Level) -> u64 {
+        self.counts[level as usize - 1].load(Ordering::Relaxed)
+    }
+
+    /// Count a record which is being logged, returning the new count.
+    fn increment(&self, level: Level) -> u64 {
+        self.counts[level as usize - 1].fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// The number of Error records logged.
+    pub fn errors(&self) -> u64 {
+        self.count(Level::Error)
+    }
+
+    /// The number of Warn records logged.
+    pub fn warns(&self) -> u64 {
+        self.count(Level::Warn)
+    }
+
+    /// The number of Info records logged.
+    pub fn infos(&self) -> u64 {
+        self.count(Level::Info)
+    }
+
+    /// The number of Debug records logged.
+    pub fn debugs(&self) -> u64 {
+        self.count(Level::Debug)
+    }
+
+    /// The number of Trace records logged.
+    pub fn traces(&self) -> u64 {
+        self.count(Level::Trace)
+    }
+
+    /// The number of records which were dropped because the
+    /// [`AsyncLogger`](async_logger::AsyncLogger)'s queue was full, see
+    /// [`Logger::with_backpressure_limit`].
+    pub fn dropped_records(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// The output stream, boxed so that it can be either a plain or buffered stderr stream.
+type Output = Box<dyn WriteColor + Send>;
+
+/// A writer which goes through `eprint!` so that the test harness can capture it.
+struct TestWriter;
+
+impl Write for TestWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        eprint!("{}", String::from_utf8_lossy(buf));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes everything to the terminal stream, plus an uncolored copy to a file. Errors writing the
+/// file are reported on the terminal, but not the other way around.
+struct Tee {
+    term: Output,
+    file: Arc<File>,
+    file_failed: bool,
+}
+
+impl Write for Tee {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // the file is written even if the terminal fails, e.g. a daemon whose stderr was closed
+        let term_res = self.term.write_all(buf);
+        // buffered output is formatted with colors if the terminal supports them, but the file
+        // should always be plain text
+        let plain = strip_escapes(buf);
+        debug_assert!(!plain.contains(&0x1b), "escape sequence in log file output");
+        match (&*self.file).write_all(&plain) {
+            Ok(()) => self.file_failed = false,
+            // only report the first error of a streak, rather than once per write
+            Err(e) if !self.file_failed => {
+                self.file_failed = true;
+                if term_res.is_ok() {
+                    self.term.reset()?;
+                    writeln!(self.term, "yall: failed to write log file: {}", e)?;
+                }
+            }
+            Err(_) => (),
+        }
+        term_res.map(|()| buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let _ = (&*self.file).flush();
+        self.term.flush()
+    }
+}
+
+/// Remove ANSI escape sequences from formatted output: CSI sequences like colors
+/// (`ESC [ ... m`) and OSC sequences like hyperlinks (`ESC ] ... BEL` or `ESC ] ... ESC \`).
+fn strip_escapes(buf: &[u8]) -> Cow<'_, [u8]> {
+    if !buf.contains(&0x1b) {
+        return Cow::Borrowed(buf);
+    }
+    let mut out = Vec::with_capacity(buf.len());
+    let mut i = 0;
+    while i < buf.len() {
+        if buf[i] != 0x1b {
+            out.push(buf[i]);
+            i += 1;
+            continue;
+        }
+        match buf.get(i + 1) {
+            Some(b'[') => {
+                // parameters and intermediates, up to and including the final byte
+                i += 2;
+                while i < buf.len() && !(0x40..=0x7e).contains(&buf[i]) {
+                    i += 1;
+                }
+                i += 1;
+            }
+            Some(b']') => {
+                i += 2;
+                while i < buf.len() && buf[i] != 0x07 && !buf[i..].starts_with(b"\x1b\\") {
+                    i += 1;
+                }
+                i += if buf[i..].starts_with(b"\x1b\\") { 2 } else { 1 };
+            }
+            // a lone escape or one yall doesn't write, drop just the escape byte
+            _ => i += 1,
+        }
+    }
+    Cow::Owned(out)
+}
+
+impl WriteColor for Tee {
+    fn supports_color(&self) -> bool {
+        self.term.supports_color()
+    }
+
+    fn set_color(&mut self, spec: &ColorSpec) -> io::Result<()> {
+        self.term.set_color(spec)
+    }
+
+    fn reset(&mut self) -> io::Result<()> {
+        self.term.reset()
+    }
+}
+
+/// A filtered non-error record saved by with_error_context_capture.
+#[derive(Debug)]
+struct ContextRecord {
+    target: String,
+    file: Option<String>,
+    line: Option<u32>,
+    msg: String,
+}
+
+/// The last few filtered non-error records, which are written before the next error.
+#[derive(Debug)]
+struct ErrorContext {
+    depth: usize,
+    records: Mutex<VecDeque<ContextRecord>>,
 }
 
 /// The main struct of this crate which implements the [`Log`] trait.
@@ -156,22 +646,144 @@ impl LevelFilterExt for LevelFilter {
 /// [`with_verbosity`](Self::with_verbosity) and then call [`init`](Self::init) or
 /// [`try_init`](Self::try_init) on it.
 pub struct Logger {
-    level: LevelFilter,
-    colors: LogColors,
-    use_full_filename: bool,
-    out: Mutex<StandardStream>,
+    state: Arc<LogState>,
+    format: FormatConfig,
+    info_color_saved: Option<ColorSpec>,
+    labels_saved: Option<LevelLabels>,
+    module_levels: Vec<(String, LevelFilter)>,
+    #[cfg(feature = "regex")]
+    target_regexes: Vec<(regex::Regex, LevelFilter)>,
+    verbose_signal: Option<Arc<AtomicBool>>,
+    #[cfg(feature = "tracing")]
+    forward_to_tracing: bool,
+    #[cfg(feature = "terminfo")]
+    color_depth: Option<terminfo::ColorDepth>,
+    lock_timeout: Option<Duration>,
+    header: bool,
+    color_mode: ColorMode,
+    color_choice: ColorChoice,
+    flush_level: Option<Level>,
+    sample_rates: [u32; 5],
+    sample_counters: [AtomicU64; 5],
+    write_level: Level,
+    count_filtered: bool,
+    first_record_action: Option<fn(&Record)>,
+    first_record_once: Once,
+    last_error: Option<Arc<Mutex<Option<String>>>>,
+    terminal_title: bool,
+    ascii_only: bool,
+    column_number: bool,
+    #[cfg(feature = "sentry")]
+    sentry_dsn: Option<sentry::types::Dsn>,
+    #[cfg(feature = "sentry")]
+    sentry: Option<sentry::ClientInitGuard>,
+    output_lock: OutputLock,
+    init_message: Option<(String, Level)>,
+    max_allowed_level: Option<LevelFilter>,
+    line_buffered: bool,
+    #[cfg(feature = "encoding")]
+    encoding: OutputEncoding,
+    fallback: Option<Mutex<StandardStream>>,
+    in_fallback: AtomicBool,
+    eprint_on_failure: bool,
+    panic_on_failure: bool,
+    output_is_stderr: bool,
+    tee_file: Option<Arc<File>>,
+    write_sentinel: Option<Mutex<()>>,
+    recent: Option<Arc<RingBuffer>>,
+    watermarks: Vec<Watermark>,
+    section_separator: String,
+    sections: Mutex<Vec<String>>,
+    error_context: Option<ErrorContext>,
+    recover_poison: bool,
+    poison_warned: AtomicBool,
+    drop_warn: bool,
+    initialized: bool,
+    pid_file: Option<PathBuf>,
+    pid_file_written: bool,
+    backpressure: Option<(usize, BackpressurePolicy)>,
+    #[cfg(feature = "net")]
+    tcp_buffer_size: Arc<AtomicUsize>,
+    low_out: Option<(Level, Mutex<Output>)>,
+    dry_run_saved: Option<SavedOutput>,
+    out: Mutex<Output>,
+}
+
+/// The outputs replaced by [`Logger::dry_run`], restored when it's disabled again.
+struct SavedOutput {
+    out: Mutex<Output>,
+    low_out: Option<(Level, Mutex<Output>)>,
+    output_is_stderr: bool,
 }
 
-// StandardStream doesn't impl Debug, so we can't derive it. Instead do this manual implementation
-// with a dummy value for out.
+// The output stream doesn't impl Debug, so we can't derive it. Instead do this manual
+// implementation with a dummy value for out.
 impl fmt::Debug for Logger {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_struct("Logger")
-            .field("level", &self.level)
-            .field("colors", &self.colors)
-            .field("use_full_filename", &self.use_full_filename)
-            .field("out", &"Mutex<termcolor::StandardStream::stderr>")
-            .finish()
+        let mut d = f.debug_struct("Logger");
+        d.field("state", &self.state).field("format", &self.format);
+        #[cfg(feature = "tracing")]
+        d.field("forward_to_tracing", &self.forward_to_tracing);
+        #[cfg(feature = "terminfo")]
+        d.field("color_depth", &self.color_depth);
+        #[cfg(feature = "regex")]
+        d.field("target_regexes", &self.target_regexes);
+        d.field("lock_timeout", &self.lock_timeout);
+        d.field("header", &self.header);
+        d.field("color_mode", &self.color_mode);
+        d.field("color_choice", &self.color_choice);
+        d.field("flush_level", &self.flush_level);
+        d.field("sample_rates", &self.sample_rates);
+        d.field("write_level", &self.write_level);
+        d.field("count_filtered", &self.count_filtered);
+        d.field("first_record_action", &self.first_record_action);
+        d.field("last_error", &self.last_error);
+        d.field("terminal_title", &self.terminal_title);
+        d.field("ascii_only", &self.ascii_only);
+        d.field("column_number", &self.column_number);
+        #[cfg(feature = "sentry")]
+        d.field("sentry", &(self.sentry_dsn.is_some() || self.sentry.is_some()));
+        d.field("output_lock", &self.output_lock);
+        d.field("init_message", &self.init_message);
+        d.field("max_allowed_level", &self.max_allowed_level);
+        d.field("line_buffered", &self.line_buffered);
+        #[cfg(feature = "encoding")]
+        d.field("encoding", &self.encoding);
+        d.field("fallback", &self.fallback.is_some());
+        d.field("eprint_on_failure", &self.eprint_on_failure);
+        d.field("panic_on_failure", &self.panic_on_failure);
+        d.field("output_is_stderr", &self.output_is_stderr);
+        d.field("tee_file", &self.tee_file);
+        d.field("write_sentinel", &self.write_sentinel.is_some());
+        d.field("recent", &self.recent);
+        d.field("watermarks", &self.watermarks);
+        d.field("section_separator", &self.section_separator);
+        d.field("error_context", &self.error_context.as_ref().map(|c| c.depth));
+        d.field("recover_poison", &self.recover_poison);
+        d.field("drop_warn", &self.drop_warn);
+        d.field("initialized", &self.initialized);
+        d.field("pid_file", &self.pid_file);
+        d.field("backpressure", &self.backpressure);
+        #[cfg(feature = "net")]
+        d.field("tcp_buffer_size", &self.tcp_buffer_size);
+        d.field("low_out", &self.low_out.as_ref().map(|(level, _)| level));
+        d.field("dry_run", &self.dry_run_saved.is_some());
+        d.field("out", &"Mutex<Box<dyn termcolor::WriteColor>>").finish()
+    }
+}
+
+/// Warn if the Logger was configured but never registered, see [`Logger::with_drop_warn`], and
+/// remove the PID file from [`Logger::with_pid_file`].
+impl Drop for Logger {
+    fn drop(&mut self) {
+        if self.drop_warn && !self.initialized && !thread::panicking() {
+            eprintln!("WARNING: yall Logger was dropped without being initialized");
+        }
+        if self.pid_file_written {
+            if let Some(path) = &self.pid_file {
+                let _ = fs::remove_file(path);
+            }
+        }
     }
 }
 
@@ -190,14 +802,151 @@ impl Logger {
 
     /// Create a Logger with the given level.
     pub fn with_level(level: LevelFilter) -> Logger {
+        Self::with_level_and_color(level, ColorMode::default())
+    }
+
+    /// Create a Logger with the given level and color mode. This is the same as
+    /// `Logger::with_level(level).color(color)`, but only creates the output stream once.
+    pub fn with_level_and_color(level: LevelFilter, color: ColorMode) -> Logger {
+        let color_choice = color.to_color_choice();
+        Self::with_output(level, color, Box::new(StandardStream::stderr(color_choice)))
+    }
+
+    /// Create a Logger with the default Info level which writes records at `threshold` or more
+    /// severe to `high`, and less severe records to `low`. For example, with a threshold of
+    /// [`Level::Warn`], errors and warnings go to `high` while everything else goes to `low`.
+    /// This is the common "errors to stderr, everything else to stdout" pattern, see
+    /// `examples/split.rs`.
+    ///
+    /// Colors are written if each writer supports them, and the startup header goes to `high`.
+    pub fn with_priority_output(
+        high: impl WriteColor + Send + 'static,
+        low: impl WriteColor + Send + 'static,
+        threshold: Level,
+    ) -> Logger {
+        let mut logger = Self::with_output(LevelFilter::Info, ColorMode::Auto, Box::new(high));
+        logger.output_is_stderr = false;
+        logger.low_out = Some((threshold, Mutex::new(Box::new(low))));
+        logger
+    }
+
+    /// Create a Logger which is completely disabled and discards its output, for tests and
+    /// benchmarks where an API needs a Logger but no logging is wanted. Unlike
+    /// `Logger::with_level(LevelFilter::Off)`, no stderr stream is created. See also [`sink`].
+    pub fn sink() -> Logger {
+        let mut logger = Self::with_output(
+            LevelFilter::Off,
+            ColorMode::Never,
+            Box::new(NoColor::new(io::sink())),
+        );
+        logger.output_is_stderr = false;
+        logger.header = false;
+        logger
+    }
+
+    /// Create a Logger with all default settings except the level, color, and output stream.
+    fn with_output(level: LevelFilter, color: ColorMode, out: Output) -> Logger {
+        let color_choice = color.to_color_choice();
         Self {
-            level,
-            colors: LogColors::new(),
-            use_full_filename: false,
-            out: Mutex::new(StandardStream::stderr(ColorMode::default().to_color_choice())),
+            state: Arc::new(LogState::new(level)),
+            format: FormatConfig::new(),
+            info_color_saved: None,
+            labels_saved: None,
+            module_levels: Vec::new(),
+            #[cfg(feature = "regex")]
+            target_regexes: Vec::new(),
+            verbose_signal: None,
+            #[cfg(feature = "tracing")]
+            forward_to_tracing: false,
+            #[cfg(feature = "terminfo")]
+            color_depth: None,
+            lock_timeout: None,
+            header: true,
+            color_mode: color,
+            color_choice,
+            flush_level: None,
+            sample_rates: [0; 5],
+            sample_counters: Default::default(),
+            write_level: Level::Info,
+            count_filtered: false,
+            first_record_action: None,
+            first_record_once: Once::new(),
+            last_error: None,
+            terminal_title: false,
+            ascii_only: false,
+            column_number: false,
+            #[cfg(feature = "sentry")]
+            sentry_dsn: None,
+            #[cfg(feature = "sentry")]
+            sentry: None,
+            output_lock: OutputLock::Mutex,
+            init_message: None,
+            max_allowed_level: None,
+            line_buffered: false,
+            #[cfg(feature = "encoding")]
+            encoding: OutputEncoding::Utf8,
+            fallback: None,
+            in_fallback: AtomicBool::new(false),
+            eprint_on_failure: true,
+            panic_on_failure: false,
+            output_is_stderr: true,
+            tee_file: None,
+            write_sentinel: None,
+            recent: None,
+            watermarks: Vec::new(),
+            section_separator: String::from("═"),
+            sections: Mutex::new(Vec::new()),
+            error_context: None,
+            recover_poison: false,
+            poison_warned: AtomicBool::new(false),
+            drop_warn: false,
+            initialized: false,
+            pid_file: None,
+            pid_file_written: false,
+            backpressure: None,
+            #[cfg(feature = "net")]
+            tcp_buffer_size: Arc::new(AtomicUsize::new(1000)),
+            low_out: None,
+            dry_run_saved: None,
+            out: Mutex::new(out),
         }
     }
 
+    /// Create a Logger with the default Info level which writes to stderr as usual, plus a copy
+    /// without colors to `file_path`. The file is opened in append mode and created if needed.
+    ///
+    /// Errors writing the file are reported on stderr, and don't stop messages from being written
+    /// to stderr. Likewise, messages are still written to the file if stderr is closed. Each
+    /// message is formatted into a buffer and written at once, like with
+    /// [`stderr_line_buffered`](Self::stderr_line_buffered), so that a stderr error doesn't cut
+    /// it short in the file.
+    pub fn with_output_to_stderr_and_file(file_path: impl AsRef<Path>) -> io::Result<Logger> {
+        let file = OpenOptions::new().create(true).append(true).open(file_path)?;
+        let mut logger = Logger::new();
+        logger.line_buffered = true;
+        logger.tee_file = Some(Arc::new(file));
+        logger.reopen_stderr();
+        Ok(logger)
+    }
+
+    /// Create a Logger which only shows Error messages, for tools which should be completely
+    /// silent unless something goes wrong. Same as
+    /// `Logger::with_level(LevelFilter::Error).no_header(true)`.
+    pub fn quiet_unless_error() -> Logger {
+        Self::with_level(LevelFilter::Error).no_header(true)
+    }
+
+    /// Create a Logger which starts out only showing Error messages, like
+    /// [`quiet_unless_error`](Self::quiet_unless_error), but shows Info messages as well once
+    /// `signal` is set. This allows turning on more output after the fact without restarting,
+    /// e.g. from a signal handler.
+    pub fn quiet_unless_error_or(signal: Arc<AtomicBool>) -> Logger {
+        let mut logger = Self::quiet_unless_error();
+        logger.verbose_signal = Some(signal);
+        logger.update_override_max();
+        logger
+    }
+
     /// Create a Logger with the given "verbosity" number. Useful for translating a number of -v
     /// flags in command-line arguments.
     ///
@@ -208,101 +957,1835 @@ impl Logger {
 
     /// Increase the verbosity level by the amount given. Takes a `u8` as returned by
     /// `clap::ArgMatches::get_count`.
-    pub fn verbose(mut self, change: u8) -> Logger {
-        self.level = self.level.add(change);
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn verbose(self, change: u8) -> Logger {
+        self.state.store_level(self.level().add(change));
         self
     }
 
     /// Decrease the verbosity level by the amount given. Takes a `u8` as returned by
     /// `clap::ArgMatches::get_count`.
-    pub fn quiet(mut self, change: u8) -> Logger {
-        self.level = self.level.sub(change);
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn quiet(self, change: u8) -> Logger {
+        self.state.store_level(self.level().sub(change));
         self
     }
 
-    /// Sets the color mode, see [`ColorMode`] for details.
-    pub fn color(mut self, c: ColorMode) -> Logger {
-        // we can't change the ColorChoice of a StandardStream, but we can just re-create it
-        self.out = Mutex::new(StandardStream::stderr(c.to_color_choice()));
+    /// Adjust the verbosity level by a signed amount, where positive values are more verbose and
+    /// negative values are more quiet, like a combination of [`verbose`](Self::verbose) and
+    /// [`quiet`](Self::quiet). Useful when the adjustment comes from a config file.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn adjust(self, delta: i8) -> Logger {
+        self.state.store_level(self.level().adjust(delta));
         self
     }
 
-    /// By default, yall will shorten the filename displayed in Debug and Trace logs by removing
-    /// a "src/" prefix and ".rs" suffix, if present. Use this function to disable that and print
-    /// the full unchanged filename.
-    pub fn full_filename(mut self, full: bool) -> Logger {
-        self.use_full_filename = full;
+    /// Set a function to call whenever the level is changed at runtime with
+    /// [`LogState::set_level`]. It receives the old and new levels, and can be used to react to
+    /// level changes, e.g. to enable expensive diagnostics only when debug logging is on.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn on_level_change(self, f: fn(LevelFilter, LevelFilter)) -> Logger {
+        *self.state.on_level_change.lock().unwrap() = Some(f);
         self
     }
 
-    /// Register this as the global logger with the [`log`](::log) crate. May fail if the application has
-    /// already set a logger.
-    pub fn try_init(self) -> Result<(), SetLoggerError> {
-        log::set_max_level(self.level);
-        log::set_boxed_logger(Box::new(self))
+    /// Set the log level for a module and all its submodules, overriding the global level. For
+    /// example, `.module_level("hyper", LevelFilter::Warn)` hides Info messages from the `hyper`
+    /// crate, and `.module_level("myapp::db", LevelFilter::Trace)` shows everything from one
+    /// module. When multiple modules match a record's target, the most specific one is used.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn module_level(mut self, module: &str, level: LevelFilter) -> Logger {
+        self.module_levels.retain(|(m, _)| m != module);
+        self.module_levels.push((module.to_owned(), level));
+        self.update_override_max();
+        self
     }
 
-    /// Same as [`try_init`](Self::try_init) but panic on failure.
-    pub fn init(self) {
-        self.try_init().expect("failed to initialize logger");
+    /// Set the log level for all targets matching a regular expression, overriding both the global
+    /// level and [`module_level`](Self::module_level). For example,
+    /// `.with_target_filter_regex(r"::tests$", LevelFilter::Trace)` shows everything from test
+    /// modules. The pattern isn't anchored, so use `^` and `$` to match whole targets. When
+    /// multiple patterns match, the one added first is used.
+    ///
+    /// Returns an error if the pattern isn't a valid regex. Requires the `regex` feature.
+    #[cfg(feature = "regex")]
+    pub fn with_target_filter_regex(
+        mut self,
+        pattern: &str,
+        level: LevelFilter,
+    ) -> Result<Logger, regex::Error> {
+        let re = self.quiet_on_err(regex::Regex::new(pattern))?;
+        self.target_regexes.push((re, level));
+        self.update_override_max();
+        Ok(self)
     }
 
-    /// Internal wrapper function for the meat of the logging that returns a Result, in case the
-    /// termcolors printing fails somehow. Assumes that we've already checked that the record's
-    /// log level is in fact enabled.
-    fn print_log(&self, r: &Record) -> io::Result<()> {
-        let level = r.level();
-
-        // strip "src/" prefix and ".rs" suffix
-        let mut filename = r.file().unwrap_or("?");
-        if !self.use_full_filename && (level == Level::Debug || level == Level::Trace) {
-            // we could use str::strip_{prefix,suffix} here, but they're not stable until
-            // rust 1.45 and return Options which is kinda clunky.
-            if filename.starts_with("src/") {
-                filename = &filename[4..];
-            }
-            if filename.ends_with(".rs") {
-                filename = &filename[..(filename.len() - 3)];
-            }
+    /// Silence all log messages from a dependency, e.g. `.disable_for_crate("hyper")`. This is
+    /// exactly the same as `.module_level(krate, LevelFilter::Off)`. Crate names with dashes
+    /// should be written with underscores, the same as in Rust code.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn disable_for_crate(self, krate: &str) -> Logger {
+        self.module_level(krate, LevelFilter::Off)
+    }
+
+    /// Silence several dependencies at once, see [`disable_for_crate`](Self::disable_for_crate).
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn disable_for_crates(mut self, krates: &[&str]) -> Logger {
+        for krate in krates.iter() {
+            self = self.disable_for_crate(krate);
         }
+        self
+    }
 
-        let mut out = self.out.lock().unwrap();
-        out.set_color(self.colors.get(level))?;
-        match level {
-            Level::Error => writeln!(out, "[ERROR] {}", r.args()),
-            Level::Warn => writeln!(out, "[WARN] {}", r.args()),
-            Level::Info => writeln!(out, "{}", r.args()),
-            Level::Debug => {
-                writeln!(out, "[DEBUG][{}:{}] {}", filename, r.line().unwrap_or(0), r.args())
-            }
-            Level::Trace => {
-                writeln!(out, "[TRACE][{}:{}] {}", filename, r.line().unwrap_or(0), r.args())
+    /// Update the state's override_max to the most verbose level which could be enabled by
+    /// something other than the global level.
+    fn update_override_max(&self) {
+        let mut max = self.module_levels.iter().map(|(_, l)| *l).max().unwrap_or(LevelFilter::Off);
+        #[cfg(feature = "regex")]
+        for (_, level) in self.target_regexes.iter() {
+            max = max.max(*level);
+        }
+        if self.verbose_signal.is_some() {
+            max = max.max(LevelFilter::Info);
+        }
+        if self.count_filtered || self.recent.is_some() || self.error_context.is_some() {
+            // every record has to reach the logger for it to be counted or saved
+            max = LevelFilter::Trace;
+        }
+        self.state.override_max.store(max.to_int(), Ordering::Relaxed);
+    }
+
+    /// Apply `RUST_LOG`-style filter directives, see [`env_filter`] for details. A directive
+    /// without a module sets the global level, and the rest are passed to
+    /// [`module_level`](Self::module_level). For example, to configure the logger like
+    /// `env_logger`:
+    ///
+    /// ```no_run
+    /// yall::Logger::new().env_filter(yall::EnvFilter::from_default_env()).init();
+    /// ```
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn env_filter(mut self, filter: EnvFilter) -> Logger {
+        for (module, level) in filter.into_directives() {
+            self = match module {
+                Some(module) => self.module_level(&module, level),
+                None => {
+                    self.state.store_level(level);
+                    self
+                }
+            };
+        }
+        self
+    }
+
+    /// The level which applies to records with the given target, based on the module levels and
+    /// the global level.
+    fn level_for(&self, target: &str) -> LevelFilter {
+        #[cfg(feature = "regex")]
+        if let Some((_, level)) = self.regex_for(target) {
+            return *level;
+        }
+        self.module_for(target).map(|(_, level)| *level).unwrap_or_else(|| self.level())
+    }
+
+    /// The most specific module level which matches the given target, if any.
+    fn module_for(&self, target: &str) -> Option<&(String, LevelFilter)> {
+        let mut best: Option<&(String, LevelFilter)> = None;
+        for entry in self.module_levels.iter() {
+            let module = entry.0.as_str();
+            let matches = target.starts_with(module)
+                && (target.len() == module.len() || target[module.len()..].starts_with("::"));
+            let longer = match best {
+                Some((m, _)) => module.len() > m.len(),
+                None => true,
+            };
+            if matches && longer {
+                best = Some(entry);
             }
-        }?;
-        out.reset()?;
-        Ok(())
+        }
+        best
     }
-}
 
-impl Log for Logger {
-    fn enabled(&self, m: &Metadata) -> bool {
-        m.level() <= self.level
+    /// The first target regex matching `target`.
+    #[cfg(feature = "regex")]
+    fn regex_for(&self, target: &str) -> Option<&(regex::Regex, LevelFilter)> {
+        self.target_regexes.iter().find(|(re, _)| re.is_match(target))
     }
 
-    fn log(&self, r: &Record) {
-        if !self.enabled(r.metadata()) {
-            return;
+    /// Whether any module levels or target regexes are set.
+    fn has_overrides(&self) -> bool {
+        #[cfg(feature = "regex")]
+        if !self.target_regexes.is_empty() {
+            return true;
         }
+        !self.module_levels.is_empty()
+    }
 
-        if let Err(e) = self.print_log(r) {
-            // uh oh, something in termcolor failed
-            eprintln!("LOGGING ERROR: failed to write log message because of '{}'", e);
-            eprintln!("Original message: {}: {}", r.level(), r.args());
+    /// Check whether a record with the given level and target would be logged, and if not, which
+    /// filter would reject it. Useful for debugging missing log output.
+    pub fn would_filter(&self, level: Level, target: &str) -> FilterDecision {
+        let metadata = Metadata::builder().level(level).target(target).build();
+        if self.enabled(&metadata) {
+            return FilterDecision::Pass;
+        }
+        #[cfg(feature = "regex")]
+        if let Some((re, _)) = self.regex_for(target) {
+            return FilterDecision::FilteredByTargetRegex(re.to_string());
+        }
+        match self.module_for(target) {
+            Some((module, _)) => FilterDecision::FilteredByModuleOverride(module.clone()),
+            None => FilterDecision::FilteredByGlobalLevel,
         }
     }
 
-    fn flush(&self) {
-        let mut out = self.out.lock().unwrap();
-        let _ = out.flush();
+    /// A human-readable explanation of [`would_filter`](Self::would_filter), like
+    /// `FILTERED: global level is Info, record level is Debug`.
+    pub fn filter_chain_report(&self, level: Level, target: &str) -> String {
+        let (filter, filter_level) = match self.module_for(target) {
+            Some((module, l)) => (format!("module level for {}", module), *l),
+            None => (String::from("global level"), self.level()),
+        };
+        #[cfg(feature = "regex")]
+        let (filter, filter_level) = match self.regex_for(target) {
+            Some((re, l)) => (format!("target regex /{}/", re), *l),
+            None => (filter, filter_level),
+        };
+        let result = match self.would_filter(level, target) {
+            FilterDecision::Pass => "PASS",
+            _ => "FILTERED",
+        };
+        let mut report =
+            format!("{}: {} is {:?}, record level is {:?}", result, filter, filter_level, level);
+        if self.verbose_signal.as_ref().is_some_and(|s| s.load(Ordering::Relaxed)) {
+            report += " (verbose signal enables Info)";
+        }
+        report
+    }
+
+    /// Get the currently configured log level.
+    pub fn level(&self) -> LevelFilter {
+        self.state.level()
+    }
+
+    /// Sets the color mode, see [`ColorMode`] for details.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn color(mut self, c: ColorMode) -> Logger {
+        // we can't change the ColorChoice of a StandardStream, but we can just re-create it
+        self.color_mode = c;
+        self.color_choice = c.to_color_choice();
+        self.reopen_stderr();
+        self
+    }
+
+    /// Sets the colors used for each level, see [`ColorScheme`] for the available presets.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn color_scheme(mut self, scheme: ColorScheme) -> Logger {
+        self.format.colors = scheme.into_colors();
+        self
+    }
+
+    /// Only apply the level's color to the bracketed prefix like `[ERROR]`, and write the message
+    /// itself in the terminal's default color. Some people find this easier to read than
+    /// coloring the whole line.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn colorize_brackets(mut self, enabled: bool) -> Logger {
+        self.format.brackets_only = enabled;
+        self
+    }
+
+    /// Write the level and target in fixed-width columns for line-oriented tools like `awk` and
+    /// `cut`. The level is padded to `level_width` characters and the target to `target_width`,
+    /// so `with_output_width(8, 24)` puts the level in columns 1-8 and the target in columns
+    /// 9-32. Longer values are truncated with `…`. Info messages get a blank level column.
+    ///
+    /// The target is only shown if [`Field::Target`] is in the field order, see
+    /// [`with_field_order`](Self::with_field_order).
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_output_width(mut self, level_width: usize, target_width: usize) -> Logger {
+        self.format.fixed_width = Some((level_width, target_width));
+        self
+    }
+
+    /// Prefix every line with the machine's hostname, e.g. `[myserver] [ERROR] disk full`, for
+    /// aggregating logs from multiple hosts. The hostname is looked up once when this is called,
+    /// and if that fails, no prefix is shown.
+    ///
+    /// Requires the `hostname` feature.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    #[cfg(feature = "hostname")]
+    pub fn with_hostname(mut self, enabled: bool) -> Logger {
+        let hostname = if enabled { hostname::get().ok() } else { None };
+        let hostname = hostname.map(|h| h.to_string_lossy().into_owned());
+        self.format = mem::take(&mut self.format).hostname(hostname.as_deref());
+        self
+    }
+
+    /// Prefix every line with `[name] `, like syslog's `ident`, to identify the program in log
+    /// files shared by several programs. This comes after the hostname, if that's enabled.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_program_name(mut self, name: &str) -> Logger {
+        self.format = mem::take(&mut self.format).program_name(name);
+        self
+    }
+
+    /// Like [`with_program_name`](Self::with_program_name), using the file name of the current
+    /// executable. If that can't be determined, no prefix is added.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_auto_program_name(self) -> Logger {
+        let exe = std::env::current_exe().ok();
+        match exe.as_ref().and_then(|e| e.file_name()) {
+            Some(name) => self.with_program_name(&name.to_string_lossy()),
+            None => self,
+        }
+    }
+
+    /// Show an error attached to log records as the key-value pair `key`, including its chain of
+    /// sources, at the end of the line. This keeps the message itself short while preserving the
+    /// full error, e.g. `log::error!(err:err; "request failed")` with
+    /// `.with_structured_error_field("err")` shows
+    /// `[ERROR] request failed err=connection lost: broken pipe`.
+    ///
+    /// Requires the `kv` feature.
+    #[cfg(feature = "kv")]
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_structured_error_field(mut self, key: &str) -> Logger {
+        self.format = mem::take(&mut self.format).structured_error_field(key);
+        self
+    }
+
+    /// Replace the level field with a header showing all of each record's metadata, like
+    /// `[ERROR target=myapp::db file=src/db.rs:42 module=myapp::db] connection refused`. Fields
+    /// which the record doesn't have are left out, and Info messages are labeled too. This is
+    /// verbose, but useful for debugging where messages come from in large applications.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_log_record_metadata(mut self, enabled: bool) -> Logger {
+        self.format = mem::take(&mut self.format).log_record_metadata(enabled);
+        self
+    }
+
+    /// Dim the `[file:line]` location field shown for Debug and Trace messages, independently of
+    /// the level's color, so that the message itself stands out.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn dim_location(mut self, enabled: bool) -> Logger {
+        self.format.dim_location = enabled;
+        self
+    }
+
+    /// Use `spec` for the fields around the message, like `[ERROR]` and `[file:line]`, at every
+    /// level rather than the level's color. Combined with
+    /// [`with_message_color`](Self::with_message_color), this colors the two parts of each line
+    /// independently of the level.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_bracket_color(mut self, spec: ColorSpec) -> Logger {
+        self.format = mem::take(&mut self.format).bracket_color(spec);
+        self
+    }
+
+    /// Use `spec` for the message text at every level rather than the level's color. This takes
+    /// priority over [`colorize_brackets`](Self::colorize_brackets).
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_message_color(mut self, spec: ColorSpec) -> Logger {
+        self.format = mem::take(&mut self.format).message_color(spec);
+        self
+    }
+
+    /// Color Info messages green rather than leaving them plain. To use a different color, set it
+    /// with [`ColorScheme::custom`] instead. Disabling this restores the Info color from before
+    /// it was enabled, and leaves the color alone if it wasn't.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn color_info(mut self, enabled: bool) -> Logger {
+        if enabled {
+            let green = ColorSpec::new().set_fg(Some(Color::Green)).to_owned();
+            let prev = self.format.colors.get(Level::Info).clone();
+            self.info_color_saved.get_or_insert(prev);
+            self.format.colors.set(Level::Info, green);
+        } else if let Some(prev) = self.info_color_saved.take() {
+            self.format.colors.set(Level::Info, prev);
+        }
+        self
+    }
+
+    /// By default, yall will shorten the filename displayed in Debug and Trace logs by removing
+    /// a "src/" prefix and ".rs" suffix, if present. Use this function to disable that and print
+    /// the full unchanged filename.
+    ///
+    /// This is the same as [`with_debug_source_display`](Self::with_debug_source_display) with
+    /// [`SourceDisplayMode::FullPath`] or [`SourceDisplayMode::FileAndLine`].
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn full_filename(mut self, full: bool) -> Logger {
+        self.format = mem::take(&mut self.format).full_filename(full);
+        self
+    }
+
+    /// Set how the source location is shown for Debug and Trace messages, see
+    /// [`SourceDisplayMode`] for the options. The default is
+    /// [`FileAndLine`](SourceDisplayMode::FileAndLine).
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_debug_source_display(mut self, mode: SourceDisplayMode) -> Logger {
+        self.format = mem::take(&mut self.format).source_display(mode);
+        self
+    }
+
+    /// Choose whether the filename, module path, both, or neither are shown in the location field
+    /// of Debug and Trace messages. This is a simpler version of
+    /// [`with_debug_source_display`](Self::with_debug_source_display), see [`LocationDisplay`].
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn location_display(self, mode: LocationDisplay) -> Logger {
+        self.with_debug_source_display(mode.into())
+    }
+
+    /// Show the column number as well as the line in the location field, like `[main:42:5]`.
+    ///
+    /// This currently has no effect, because [`Record`] doesn't carry column information and the
+    /// [`log`](::log) macros don't capture it. The setting is kept so that it can take effect if
+    /// a future version of `log` adds column support.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_column_number(mut self, enabled: bool) -> Logger {
+        self.column_number = enabled;
+        self
+    }
+
+    /// Set the order in which fields are written in each log line. Fields which aren't included
+    /// are omitted entirely. The default order is `Level`, `Location`, `Message`.
+    ///
+    /// Timestamps and targets aren't shown by default, but can be enabled by including
+    /// [`Field::Timestamp`] and [`Field::Target`] here.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_field_order(mut self, fields: impl IntoIterator<Item = Field>) -> Logger {
+        self.format.field_order = fields.into_iter().collect();
+        self
+    }
+
+    /// Forward all log records to the current `tracing` subscriber rather than writing them to
+    /// stderr. Records are emitted as `tracing` events with the equivalent level, and the
+    /// original target, file, and line are attached as `log.target`, `log.file`, and `log.line`
+    /// fields. Level filtering is still done by yall before records are forwarded.
+    ///
+    /// This is useful when migrating an application from `log` to `tracing`. Requires the
+    /// `tracing` feature.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    #[cfg(feature = "tracing")]
+    pub fn forward_to_tracing(mut self) -> Logger {
+        self.forward_to_tracing = true;
+        self
+    }
+
+    /// Check the terminal's color support with terminfo rather than only checking whether stderr
+    /// is a tty. If terminfo reports no colors, color is disabled (unless it was forced on with
+    /// [`ColorMode::Always`]). Otherwise, RGB and 256-palette colors which the terminal can't show
+    /// are converted to the closest color it supports when the logger is [built](Self::build).
+    /// If terminfo isn't available, the usual tty detection is used.
+    ///
+    /// Requires the `terminfo` feature.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    #[cfg(feature = "terminfo")]
+    pub fn with_color_support_detection_via_terminfo(mut self, enabled: bool) -> Logger {
+        self.color_depth = if enabled { terminfo::detect() } else { None };
+        if self.color_depth == Some(terminfo::ColorDepth::None)
+            && self.color_mode == ColorMode::Auto
+        {
+            self.color_choice = ColorChoice::Never;
+            self.reopen_stderr();
+        }
+        self
+    }
+
+    /// Give up waiting for the output lock after `timeout` rather than blocking forever. When the
+    /// timeout expires, the message is printed directly to stderr along with a warning about a
+    /// possible deadlock.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_writer_lock_timeout(mut self, timeout: Duration) -> Logger {
+        self.lock_timeout = Some(timeout);
+        self
+    }
+
+    /// Disable the header line which is normally written by [`init`](Self::init) and
+    /// [`try_init`](Self::try_init), see [`write_header`](Self::write_header).
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn no_header(mut self, disabled: bool) -> Logger {
+        self.header = !disabled;
+        self
+    }
+
+    /// Write a header line describing this logging session, which makes log files
+    /// self-describing. This is called automatically when the logger is registered unless
+    /// disabled with [`no_header`](Self::no_header), but can also be used to write the header
+    /// somewhere else. The header looks like
+    ///
+    /// ```text
+    /// # yall logger started 2020-06-01T12:34:56Z level=info pid=12345
+    /// ```
+    ///
+    /// The header is always written regardless of the log level.
+    pub fn write_header<W: Write + ?Sized>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(
+            out,
+            "# yall logger started {} level={} pid={}",
+            (self.format.time_source)(),
+            self.level().as_lower_str(),
+            std::process::id()
+        )
+    }
+
+    /// Set the label text shown for each level, see [`LevelLabels`] for details.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn level_labels(mut self, labels: LevelLabels) -> Logger {
+        self.format.labels = labels;
+        self
+    }
+
+    /// Use syslog-style severity names for level labels, which makes the output compatible with
+    /// log parsers that expect standard syslog severities. See [`LevelLabels::syslog`] for
+    /// details. Disabling this restores the labels from before it was enabled, and leaves them
+    /// alone if it wasn't.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn syslog_level_names(mut self, enabled: bool) -> Logger {
+        if enabled {
+            let prev = mem::replace(&mut self.format.labels, LevelLabels::syslog());
+            self.labels_saved.get_or_insert(prev);
+        } else if let Some(prev) = self.labels_saved.take() {
+            self.format.labels = prev;
+        }
+        self
+    }
+
+    /// Use compact three-character level labels so that every level prefix has the same width,
+    /// e.g. `[ERR]`, `[WRN]`, and `[INF]`. See [`LevelLabels::three_char`].
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_short_level_names(mut self) -> Logger {
+        self.format.labels = LevelLabels::three_char();
+        self
+    }
+
+    /// Set how newlines embedded in log messages are handled, see [`NewlineMode`] for details.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn newline_handling(mut self, mode: NewlineMode) -> Logger {
+        self.format.newline_mode = mode;
+        self
+    }
+
+    /// Append the time spent formatting and writing each Debug and Trace message, like
+    /// `[log=42µs]`, for finding out how much logging slows down performance-sensitive code.
+    /// Note that measuring the time adds some overhead itself. The time doesn't include waiting
+    /// for the output lock.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn show_log_overhead(mut self, enabled: bool) -> Logger {
+        self.format.show_overhead = enabled;
+        self
+    }
+
+    /// Word-wrap messages so that each line fits within `cols` columns. Continuation lines are
+    /// indented to line up with the start of the message. ANSI escape sequences in messages don't
+    /// count toward the width.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_max_line_width(mut self, cols: usize) -> Logger {
+        self.format.max_width = Some(cols);
+        self
+    }
+
+    /// Strip `prefix` from record targets when they're displayed with [`Field::Target`], which
+    /// helps with deeply nested module paths. For example,
+    /// `.with_module_prefix_strip("my_workspace::my_service::")` shows `internal::subsystem`
+    /// rather than `my_workspace::my_service::internal::subsystem`. This can be called multiple
+    /// times to strip different prefixes, and the first matching prefix is stripped.
+    ///
+    /// This only affects how targets are displayed, not filtering with
+    /// [`module_level`](Self::module_level).
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_module_prefix_strip(mut self, prefix: &str) -> Logger {
+        self.format = mem::take(&mut self.format).module_prefix_strip(prefix);
+        self
+    }
+
+    /// Abbreviate record targets when they're displayed with [`Field::Target`], using a list of
+    /// `(prefix, abbreviation)` pairs. For example,
+    /// `("my_application::internal::network", "net")` shows
+    /// `my_application::internal::network::tcp` as `net::tcp`. Like
+    /// [`with_module_prefix_strip`](Self::with_module_prefix_strip), the first matching prefix is
+    /// replaced, and filtering isn't affected.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_target_abbreviation_rules<'a>(
+        mut self,
+        rules: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Logger {
+        self.format = mem::take(&mut self.format).target_abbreviations(rules);
+        self
+    }
+
+    /// Reset the color before each newline in a message and set it again afterwards, so that
+    /// every terminal line of a multiline message has its color applied separately. This helps
+    /// with pagers and terminals which don't carry colors across lines, and applies to both
+    /// embedded newlines (see [`NewlineMode`]) and lines wrapped by
+    /// [`with_max_line_width`](Self::with_max_line_width).
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_color_reset_on_each_line(mut self, enabled: bool) -> Logger {
+        self.format.reset_each_line = enabled;
+        self
+    }
+
+    /// Use `f` to get the timestamp for each log line instead of yall's built-in UTC timestamps,
+    /// and enable timestamps by adding [`Field::Timestamp`] to the start of the field order if
+    /// it's not already there. This is also used for the [header](Self::write_header).
+    ///
+    /// This is mainly useful for tests which check log output, where `f` can return a fixed
+    /// string to make the output deterministic. It can also be used for a different timestamp
+    /// format, e.g. local time from the chrono crate.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_custom_time_source(mut self, f: fn() -> String) -> Logger {
+        self.format = mem::take(&mut self.format).time_source(f);
+        self
+    }
+
+    /// Make the `[file:line]` location in Debug and Trace messages a clickable hyperlink to the
+    /// source code, using the OSC 8 escape sequence supported by most modern terminals.
+    /// `base_url` is the URL prefix for source files, e.g.
+    /// `https://github.com/user/repo/blob/main/`, and the link target is the record's full
+    /// filename and a `#L<line>` anchor.
+    ///
+    /// Links are only written when colors are enabled, so they're automatically disabled when
+    /// stderr isn't a terminal or with [`ColorMode::Never`].
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_source_link(mut self, base_url: &str) -> Logger {
+        self.format = mem::take(&mut self.format).source_link(base_url);
+        self
+    }
+
+    /// Like [`with_source_link`](Self::with_source_link), but with a complete URL format where
+    /// `{file}` and `{line}` are replaced by the record's filename and line number, e.g.
+    /// `vscode://file/{file}:{line}` to open files in VS Code.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_ansi_hyperlink_format(mut self, format: &str) -> Logger {
+        self.format = mem::take(&mut self.format).hyperlink_format(format);
+        self
+    }
+
+    /// Replace the level field with custom text for specific levels, e.g. `[ ]` or `[✓]` for
+    /// progress-style output. Unlike [`LevelLabels`], the text isn't surrounded with brackets.
+    /// Levels which aren't listed keep their normal label.
+    ///
+    /// Combined with ANSI cursor control in the application, this can be used for simple
+    /// spinners and progress indicators.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn progress_prefix(mut self, patterns: &[(Level, &str)]) -> Logger {
+        self.format = mem::take(&mut self.format).progress_prefix(patterns);
+        self
+    }
+
+    /// Buffer output and only flush it after writing a record at `level` or more severe. For
+    /// example, `flush_on_level(Level::Error)` makes errors show up immediately (so they appear
+    /// before the program crashes) while less severe messages are buffered, which is faster when
+    /// logging lots of messages.
+    ///
+    /// By default, output is unbuffered. When buffering is enabled, call `log::logger().flush()`
+    /// before exiting so that buffered messages aren't lost.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn flush_on_level(mut self, level: Level) -> Logger {
+        self.flush_level = Some(level);
+        self.reopen_stderr();
+        self
+    }
+
+    /// Only write every `n`th record at `level`, dropping the rest. This is useful for very chatty
+    /// Debug or Trace messages, where seeing a sample is enough. Sampling is count-based rather
+    /// than time-based, and each level is sampled independently. Records which are written get a
+    /// `(sampled 1/n)` suffix. An `n` of 0 or 1 disables sampling.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn sample_rate(mut self, level: Level, n: u32) -> Logger {
+        self.sample_rates[level as usize - 1] = n;
+        self
+    }
+
+    /// Redirect the process's stderr file descriptor to `path`, so that everything written to
+    /// stderr ends up in that file, including output from dependencies which print directly to
+    /// stderr rather than using the [`log`](::log) crate. The file is created if needed and
+    /// appended to. Returns an error if the file can't be opened or redirected.
+    ///
+    /// **Warning:** this affects the entire process, not just this logger, and it takes effect
+    /// immediately rather than when the logger is registered. Child processes which inherit
+    /// stderr will also write to the file. Auto color mode is disabled, since stderr is no longer
+    /// a terminal. Only available on Unix.
+    #[cfg(unix)]
+    pub fn redirect_stderr_to_file(mut self, path: impl AsRef<Path>) -> io::Result<Logger> {
+        extern "C" {
+            fn dup2(oldfd: std::os::raw::c_int, newfd: std::os::raw::c_int) -> std::os::raw::c_int;
+        }
+
+        let file = self.quiet_on_err(OpenOptions::new().create(true).append(true).open(path))?;
+        // SAFETY: dup2 has no memory safety requirements, and file's descriptor is valid for the
+        // duration of the call. The file can be closed afterwards since fd 2 is a duplicate.
+        if unsafe { dup2(file.as_raw_fd(), 2) } < 0 {
+            return self.quiet_on_err(Err(io::Error::last_os_error()));
+        }
+
+        if self.color_choice == ColorChoice::Auto {
+            self.color_choice = ColorChoice::Never;
+            self.reopen_stderr();
+        }
+        Ok(self)
+    }
+
+    /// Write a separator line with `text` centered in it, like `──── phase 2 ────`, padded to 80
+    /// columns. This is useful for marking sections in long-running programs, e.g. the start of
+    /// each batch job. The separator is always written regardless of the log level, and has no
+    /// level label or color.
+    pub fn write_separator(&self, text: &str) -> io::Result<()> {
+        let mut out = self.lock_out()?;
+        writeln!(out, "{}", separator_line(text, 80, "─"))
+    }
+
+    /// Set the string used to draw the separator lines written by
+    /// [`begin_section`](Self::begin_section) and [`end_section`](Self::end_section), which is `═`
+    /// by default. It should be a single character wide, e.g. `=` for ASCII-only output.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_context_separator(mut self, sep: &str) -> Logger {
+        self.section_separator = String::from(sep);
+        self
+    }
+
+    /// Start a section, writing a separator line like `════ label ════` as wide as the max line
+    /// width, or 80 columns by default. Sections can be nested, and the separator is always
+    /// written regardless of the log level. See the [`context`] module for doing this with the
+    /// global logger.
+    pub fn begin_section(&self, label: &str) -> io::Result<()> {
+        self.sections.lock().unwrap_or_else(|e| e.into_inner()).push(String::from(label));
+        self.write_section(label)
+    }
+
+    /// End the most recently started section, writing a matching `════ end label ════` separator
+    /// line. Does nothing if no section is open.
+    pub fn end_section(&self) -> io::Result<()> {
+        let label = self.sections.lock().unwrap_or_else(|e| e.into_inner()).pop();
+        match label {
+            Some(label) => self.write_section(&format!("end {}", label)),
+            None => Ok(()),
+        }
+    }
+
+    /// Write a section separator, as wide as the max line width if one is set.
+    fn write_section(&self, text: &str) -> io::Result<()> {
+        let width = self.format.max_width.unwrap_or(80);
+        let mut out = self.lock_out()?;
+        writeln!(out, "{}", separator_line(text, width, &self.section_separator))
+    }
+
+    /// When writing to the primary output fails (e.g. because the disk is full), write the
+    /// message to stderr instead. Every message still tries the primary output first, so logging
+    /// goes back to it as soon as it works again. A notice is written to stderr when switching
+    /// to the fallback.
+    ///
+    /// Without this, failed messages are printed to stderr along with an error message.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_stderr_fallback(mut self, enabled: bool) -> Logger {
+        self.fallback = if enabled {
+            Some(Mutex::new(StandardStream::stderr(self.color_choice)))
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Describe this logger's configuration as a multi-line string, for debugging unexpected
+    /// logging behavior, e.g. behind a `--debug-log-config` flag. The exact format isn't stable
+    /// and shouldn't be parsed.
+    pub fn write_diagnostic_report(&self) -> String {
+        let features: Vec<&str> = [
+            ("clap", cfg!(feature = "clap")),
+            ("compress", cfg!(feature = "compress")),
+            ("encoding", cfg!(feature = "encoding")),
+            ("hostname", cfg!(feature = "hostname")),
+            ("kv", cfg!(feature = "kv")),
+            ("net", cfg!(feature = "net")),
+            ("regex", cfg!(feature = "regex")),
+            ("sentry", cfg!(feature = "sentry")),
+            ("terminfo", cfg!(feature = "terminfo")),
+            ("tokio", cfg!(feature = "tokio")),
+            ("tracing", cfg!(feature = "tracing")),
+        ]
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| *name)
+        .collect();
+        let output = if self.dry_run_saved.is_some() {
+            "dry run"
+        } else if self.low_out.is_some() {
+            "split by level"
+        } else if !self.output_is_stderr {
+            "custom writer"
+        } else if self.tee_file.is_some() {
+            "stderr and file"
+        } else {
+            "stderr"
+        };
+
+        let features = if features.is_empty() { String::from("none") } else { features.join(", ") };
+        let mut report = format!("yall {} (features: {})\n", VERSION, features);
+        report += &format!("level: {}\n", DisplayLevel(self.level()));
+        for (module, level) in self.module_levels.iter() {
+            report += &format!("module level: {} = {}\n", module, DisplayLevel(*level));
+        }
+        #[cfg(feature = "regex")]
+        for (re, level) in self.target_regexes.iter() {
+            report += &format!("target regex: /{}/ = {}\n", re, DisplayLevel(*level));
+        }
+        report += &format!("verbose signal: {}\n", self.verbose_signal.is_some());
+        report += &format!("color: {:?} ({:?})\n", self.color_mode, self.color_choice);
+        report += &format!("output: {}\n", output);
+        report += &format!("line buffered: {}\n", self.line_buffered);
+        report += &format!("flush on level: {:?}\n", self.flush_level);
+        report += &format!("stderr fallback: {}\n", self.fallback.is_some());
+        report += &format!("header: {}\n", self.header);
+        report += &format!("fields: {:?}\n", self.format.field_order);
+        for level in Level::iter() {
+            let rate = self.sample_rates[level as usize - 1];
+            if rate > 1 {
+                report += &format!("sample rate: {} 1/{}\n", level, rate);
+            }
+        }
+        report += &format!("count filtered records: {}\n", self.count_filtered);
+        report
+    }
+
+    /// Log `msg` at `level` directly through this logger, bypassing the [`log`](::log) crate's
+    /// global logger. The usual level filtering still applies. The caller's file and line are
+    /// used for the record's location, and the target is empty.
+    ///
+    /// This is useful for code which holds its own `Logger` and logs with a level that's only
+    /// known at runtime.
+    #[track_caller]
+    pub fn write_at_level(&self, level: Level, msg: &str) {
+        let caller = std::panic::Location::caller();
+        self.log(
+            &Record::builder()
+                .level(level)
+                .file(Some(caller.file()))
+                .line(Some(caller.line()))
+                .args(format_args!("{}", msg))
+                .build(),
+        );
+    }
+
+    /// Format a record the same way this logger would, without colors, and return it rather than
+    /// writing it anywhere. This is useful for sending yall-formatted messages to other
+    /// destinations like sockets or GUIs. The string includes a trailing newline, and no level
+    /// filtering is done.
+    pub fn format_record(&self, record: &Record) -> String {
+        let mut out = NoColor::new(Vec::new());
+        // writing to a Vec can't fail
+        let _ = self.print_log_to(record, &mut out);
+        String::from_utf8_lossy(&out.into_inner()).into_owned()
+    }
+
+    /// Like [`format_record`](Self::format_record), but with ANSI color escape sequences, for
+    /// display on a terminal.
+    pub fn format_record_colored(&self, record: &Record) -> String {
+        let mut out = Buffer::ansi();
+        let _ = self.print_log_to(record, &mut out);
+        String::from_utf8_lossy(out.as_slice()).into_owned()
+    }
+
+    /// Write a record to `out` with this logger's formatting, including colors if `out` supports
+    /// them. This allows routing records through yall's formatting to any destination, for
+    /// example from another [`Log`] implementation which wraps yall. No level filtering,
+    /// sampling, or flushing is done, and the Logger's own output isn't touched.
+    pub fn print_log_to(&self, record: &Record, out: &mut impl WriteColor) -> io::Result<()> {
+        write_record(record, &self.format, out)
+    }
+
+    /// Log an error and its chain of [`source`](Error::source)s at Error level, with each source
+    /// on its own indented line:
+    ///
+    /// ```text
+    /// [ERROR] failed to load config
+    ///   Caused by: failed to read config.toml
+    ///   Caused by: No such file or directory (os error 2)
+    /// ```
+    #[track_caller]
+    pub fn write_error(&self, e: &dyn Error) {
+        let mut msg = e.to_string();
+        let mut source = e.source();
+        while let Some(cause) = source {
+            msg += &format!("\n  Caused by: {}", cause);
+            source = cause.source();
+        }
+        self.write_at_level(Level::Error, &msg);
+    }
+
+    /// Set the level used for messages written with the [`fmt::Write`] implementation, which is
+    /// Info by default.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn write_level(mut self, level: Level) -> Logger {
+        self.write_level = level;
+        self
+    }
+
+    /// Count the records at each level which are filtered out, to help debug the level
+    /// configuration. The counts are available from [`suppressed_count`], and a summary line is
+    /// written when the logger is flushed or by calling [`print_suppression_summary`].
+    ///
+    /// This makes every log call reach the logger rather than being skipped by the `log` crate's
+    /// max level check, so it has some overhead for disabled Debug and Trace messages.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn count_filtered_records(mut self, enabled: bool) -> Logger {
+        self.count_filtered = enabled;
+        self.update_override_max();
+        self
+    }
+
+    /// Call `f` once, just before the first record is written, e.g. to create log directories or
+    /// print a startup banner. `f` receives that first record for context. Records which are
+    /// filtered out don't count, and `f` is only called once even if several threads log at the
+    /// same time.
+    ///
+    /// `f` must not log anything itself, since that would deadlock waiting for `f` to finish.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_first_record_action(mut self, f: fn(&Record)) -> Logger {
+        self.first_record_action = Some(f);
+        self
+    }
+
+    /// Store the message of each Error record in `store`, so that the most recent error is
+    /// available from any thread, e.g. for a health check endpoint. The caller keeps a clone of
+    /// the `Arc` to read it.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_last_error(mut self, store: Arc<Mutex<Option<String>>>) -> Logger {
+        self.last_error = Some(store);
+        self
+    }
+
+    /// Set the terminal's window title to `ERROR: <message>` whenever an error is logged, using
+    /// the OSC 0 escape sequence, so that it's obvious which window or tab has errors. Only the
+    /// first 60 characters of the message are used. This does nothing if stderr isn't a terminal
+    /// or log output isn't going to stderr.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_terminal_title_update(mut self, enabled: bool) -> Logger {
+        self.terminal_title = enabled && io::stderr().is_terminal();
+        self
+    }
+
+    /// Log `msg` at `level` as soon as the logger is registered by [`init`](Self::init) or
+    /// [`try_init`](Self::try_init), e.g. `"Starting myapp v1.2.3"`. The message is subject to the
+    /// usual level filtering.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_init_message(mut self, msg: impl Into<String>, level: Level) -> Logger {
+        self.init_message = Some((msg.into(), level));
+        self
+    }
+
+    /// Log a warning when the logger is registered if the level is more verbose than
+    /// `max_allowed`, as a guard against accidentally shipping a debug configuration. For
+    /// example, `.with_level_threshold_warning(LevelFilter::Info)` warns if the program is started
+    /// with Debug or Trace logging.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_level_threshold_warning(mut self, max_allowed: LevelFilter) -> Logger {
+        self.max_allowed_level = Some(max_allowed);
+        self
+    }
+
+    /// Keep the last `n` formatted records in memory, for dumping recent log history from a panic
+    /// hook or signal handler after a crash. Records are saved regardless of the log level, so
+    /// the buffer includes Debug and Trace messages even if they aren't shown. The returned
+    /// [`RingBuffer`] can be read from any thread with [`RingBuffer::snapshot`].
+    ///
+    /// Like [`count_filtered_records`](Self::count_filtered_records), this makes every log call
+    /// reach the logger, and every record is formatted, so it has some overhead.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_last_n_records(mut self, n: usize) -> (Logger, Arc<RingBuffer>) {
+        let recent = Arc::new(RingBuffer::new(n));
+        self.recent = Some(Arc::clone(&recent));
+        self.update_override_max();
+        (self, recent)
+    }
+
+    /// Call `action` when the number of records logged at `level` reaches `count`, e.g. to alert
+    /// when a batch job logs its 1000th error. The action receives the current count, and is
+    /// called before the record that reached the watermark is written. Multiple watermarks can
+    /// be added for each level.
+    ///
+    /// Counts are the same as [`LogState::count`], so only records which are actually logged are
+    /// counted.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_event_count_watermark(
+        mut self,
+        level: Level,
+        count: u64,
+        action: fn(u64),
+    ) -> Logger {
+        self.watermarks.push((level, count, action));
+        self
+    }
+
+    /// Keep the last `depth` non-error records which were filtered out, and write them at Error
+    /// level with a `[context]` tag just before the next error. The lines leading up to an error
+    /// are often the key to understanding it, even if they're normally too verbose to show.
+    /// Records which were already written aren't saved, and the buffer is cleared after each
+    /// error.
+    ///
+    /// Like [`count_filtered_records`](Self::count_filtered_records), this makes every log call
+    /// reach the logger, so it has some overhead.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_error_context_capture(mut self, depth: usize) -> Logger {
+        self.error_context =
+            Some(ErrorContext { depth, records: Mutex::new(VecDeque::with_capacity(depth)) });
+        self.update_override_max();
+        self
+    }
+
+    /// Format each log line into a buffer first and write it to the output with a single
+    /// `write_all` call, rather than writing each field separately. yall already holds a lock
+    /// while writing each line, but doing a single write makes it less likely for lines to get
+    /// mixed up with output from other processes or code which writes to stderr directly.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn stderr_line_buffered(mut self) -> Logger {
+        self.line_buffered = true;
+        self
+    }
+
+    /// Replace every non-ASCII character in the output with a `\uXXXX` escape, e.g. `café`
+    /// becomes `caf\u00E9`, for serial consoles, strict log parsers, or systems running with
+    /// `LANG=C` where the terminal's encoding is uncertain. Color escape sequences are unaffected.
+    /// Like with [`stderr_line_buffered`](Self::stderr_line_buffered), each line is formatted
+    /// into a buffer and written at once.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_ascii_only(mut self, enabled: bool) -> Logger {
+        self.ascii_only = enabled;
+        self
+    }
+
+    /// Write log output in a legacy encoding rather than UTF-8, for old Windows consoles using
+    /// code pages like 1252. Characters which can't be represented are replaced with `?`. Each
+    /// line is formatted into a buffer and written at once, like with
+    /// [`stderr_line_buffered`](Self::stderr_line_buffered).
+    ///
+    /// Requires the `encoding` feature.
+    #[cfg(feature = "encoding")]
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_output_encoding(mut self, enc: OutputEncoding) -> Logger {
+        self.encoding = enc;
+        self
+    }
+
+    /// Check that the configuration is valid, returning an error if it isn't. This is done
+    /// automatically by [`try_init`](Self::try_init) and [`init`](Self::init), but can be used to
+    /// check a configuration ahead of time.
+    pub fn build(mut self) -> Result<Logger, ConfigError> {
+        let validated = self.validate();
+        self.quiet_on_err(validated)?;
+
+        #[cfg(feature = "terminfo")]
+        if let Some(depth) = self.color_depth {
+            self.format.colors = terminfo::downgrade(&self.format.colors, depth);
+        }
+
+        Ok(self)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        // targets can be any string, not just module paths, so only an empty one is invalid
+        if let Some((module, _)) = self.module_levels.iter().find(|(m, _)| m.is_empty()) {
+            return Err(ConfigError::InvalidModulePattern(module.clone()));
+        }
+
+        let fields = &self.format.field_order;
+        if let Some(dup) = fields.iter().enumerate().find(|(i, f)| fields[..*i].contains(f)) {
+            return Err(ConfigError::ConflictingOptions(format!(
+                "{:?} is included in the field order more than once",
+                dup.1
+            )));
+        }
+
+        if self.output_lock != OutputLock::Mutex {
+            return Err(ConfigError::Unsupported(format!(
+                "output lock type {:?} isn't implemented yet",
+                self.output_lock
+            )));
+        }
+        Ok(())
+    }
+
+    /// Write log messages with `eprint!` rather than directly to stderr, so that they're captured
+    /// by Rust's test harness and only shown for failing tests, like `println!` output. Outside
+    /// of tests this still ends up on stderr, but without colors.
+    ///
+    /// This works for tests in any crate, because the test harness captures the `print!` family
+    /// of macros at runtime. Checking `cfg(test)` in yall wouldn't work since that's only set
+    /// when testing yall itself.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn log_to_test_output(mut self) -> Logger {
+        self.output_is_stderr = false;
+        self.out = Mutex::new(Box::new(NoColor::new(TestWriter)));
+        self
+    }
+
+    /// Write to a log file which is rotated based on its size and/or age, rather than stderr. See
+    /// [`RotateConfig`] and the [`rotate`] module for details. Colors are never written to the
+    /// file. Returns an error if the file can't be opened, or if `compress_old` is set without
+    /// the `compress` feature.
+    pub fn with_rotating_file(mut self, config: RotateConfig) -> io::Result<Logger> {
+        let file = self.quiet_on_err(rotate::RotatingFile::open(config))?;
+        self.output_is_stderr = false;
+        self.out = Mutex::new(Box::new(NoColor::new(file)));
+        Ok(self)
+    }
+
+    /// Send each log message as a plain-text UDP datagram to `addr` rather than writing to
+    /// stderr, for network log aggregation. UDP never blocks on the network, so messages are
+    /// simply lost if the receiver isn't listening or can't keep up. Colors are never sent, and
+    /// messages longer than 65000 bytes are truncated.
+    ///
+    /// This enables [`stderr_line_buffered`](Self::stderr_line_buffered) so that each message is
+    /// sent as a single datagram. Returns an error if the local socket can't be created. Requires
+    /// the `net` feature.
+    #[cfg(feature = "net")]
+    pub fn with_output_to_udp(mut self, addr: std::net::SocketAddr) -> io::Result<Logger> {
+        let writer = self.quiet_on_err(net::UdpWriter::new(addr))?;
+        self.output_is_stderr = false;
+        self.line_buffered = true;
+        self.out = Mutex::new(Box::new(NoColor::new(writer)));
+        Ok(self)
+    }
+
+    /// Send log messages over a TCP connection to `addr` rather than writing to stderr, as
+    /// newline-delimited plain text without colors. The connection is opened right away.
+    ///
+    /// With `reconnect`, a failed connection is retried with exponential backoff up to 30
+    /// seconds between attempts, and messages are queued in the meantime, see
+    /// [`with_tcp_buffer_size`](Self::with_tcp_buffer_size). Connection attempts happen in the
+    /// logging thread and wait up to a second each. Without `reconnect`, errors are handled like
+    /// any other output failure, see [`eprint_on_log_failure`](Self::eprint_on_log_failure).
+    ///
+    /// Returns an error if the initial connection fails and `reconnect` isn't set. Requires the
+    /// `net` feature.
+    #[cfg(feature = "net")]
+    pub fn with_output_to_tcp(
+        mut self,
+        addr: std::net::SocketAddr,
+        reconnect: bool,
+    ) -> io::Result<Logger> {
+        let writer = net::TcpWriter::connect(addr, reconnect, Arc::clone(&self.tcp_buffer_size));
+        let writer = self.quiet_on_err(writer)?;
+        self.output_is_stderr = false;
+        self.line_buffered = true;
+        self.out = Mutex::new(Box::new(NoColor::new(writer)));
+        Ok(self)
+    }
+
+    /// Set the number of messages queued while [`with_output_to_tcp`](Self::with_output_to_tcp)
+    /// is reconnecting. Once the queue is full, new messages are dropped until the connection
+    /// is restored. The default is 1000. Requires the `net` feature.
+    #[cfg(feature = "net")]
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_tcp_buffer_size(self, n: usize) -> Logger {
+        self.tcp_buffer_size.store(n, Ordering::Relaxed);
+        self
+    }
+
+    /// Write newline-delimited log messages without colors to the Unix domain stream socket at
+    /// `path` rather than stderr, for log aggregators like Vector, Fluentd, or rsyslog which
+    /// accept input on a socket.
+    ///
+    /// If the socket can't be connected to, e.g. because the aggregator hasn't started yet, a
+    /// background thread retries every second, and messages logged in the meantime are
+    /// discarded. The same happens if the connection is lost later. Only available on Unix.
+    #[cfg(unix)]
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_output_to_unix_socket(mut self, path: impl AsRef<Path>) -> Logger {
+        let writer = ipc::UnixSocketWriter::connect(path.as_ref().to_owned());
+        self.output_is_stderr = false;
+        self.line_buffered = true;
+        self.out = Mutex::new(Box::new(NoColor::new(writer)));
+        self
+    }
+
+    /// Write log messages to `writer` without colors rather than stderr, e.g. a child process's
+    /// stdin or an anonymous pipe from the `os_pipe` crate for passing log output to another
+    /// process.
+    ///
+    /// The writer isn't buffered, each message is formatted first and then passed to it with a
+    /// single write, so the reader sees it as soon as it's logged.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_pipe_writer(mut self, writer: impl Write + Send + 'static) -> Logger {
+        self.output_is_stderr = false;
+        self.line_buffered = true;
+        self.out = Mutex::new(Box::new(NoColor::new(writer)));
+        self
+    }
+
+    /// Discard all output, for testing a configuration without any I/O. Filtering, counting, and
+    /// hooks like [`on_level_change`](Self::on_level_change) all work as usual, and records are
+    /// still formatted, but the result is written to [`io::sink`]. Disabling dry run mode goes
+    /// back to the output that was in place before it was enabled, and does nothing if it wasn't.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn dry_run(mut self, enabled: bool) -> Logger {
+        if enabled && self.dry_run_saved.is_none() {
+            let sink: Output = Box::new(NoColor::new(io::sink()));
+            self.dry_run_saved = Some(SavedOutput {
+                out: mem::replace(&mut self.out, Mutex::new(sink)),
+                low_out: self.low_out.take(),
+                output_is_stderr: self.output_is_stderr,
+            });
+            self.output_is_stderr = false;
+        } else if !enabled {
+            if let Some(saved) = self.dry_run_saved.take() {
+                self.out = saved.out;
+                self.low_out = saved.low_out;
+                self.output_is_stderr = saved.output_is_stderr;
+                // pick up color or buffering changes made while in dry run mode
+                self.reopen_stderr();
+            }
+        }
+        self
+    }
+
+    /// Detect log writes from multiple threads overlapping, as a diagnostic aid when debugging
+    /// race conditions. If a record is written while another thread is in the middle of writing
+    /// one, ` [concurrent-write-detected]` is appended to its message. Writes are serialized
+    /// either way, so this doesn't change what's written otherwise.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_concurrent_writes_detection(mut self, enabled: bool) -> Logger {
+        self.write_sentinel = if enabled { Some(Mutex::new(())) } else { None };
+        self
+    }
+
+    /// Keep logging after a thread panics while holding the output lock, rather than panicking
+    /// on every later log call because the lock is poisoned. The output stream is reused as-is,
+    /// and a warning is written the first time this happens.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_write_lock_fallback_to_new_stream(mut self, enabled: bool) -> Logger {
+        self.recover_poison = enabled;
+        self
+    }
+
+    /// When a log message can't be written, print an error and the original message with
+    /// `eprintln!`. This is enabled by default, disable it to silently drop messages that can't
+    /// be written, e.g. for daemons where stderr may be closed.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn eprint_on_log_failure(mut self, enabled: bool) -> Logger {
+        self.eprint_on_failure = enabled;
+        self
+    }
+
+    /// Panic when a log message can't be written, for strict testing environments where failing
+    /// to log is a bug. This takes priority over
+    /// [`eprint_on_log_failure`](Self::eprint_on_log_failure).
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn panic_on_log_failure(mut self, enabled: bool) -> Logger {
+        self.panic_on_failure = enabled;
+        self
+    }
+
+    /// Report every Error and Warn record to [Sentry](https://sentry.io) using `dsn`, in
+    /// addition to the normal output. An empty DSN disables this, so the DSN can come straight
+    /// from an optional config setting.
+    ///
+    /// The Sentry client is started when the Logger is registered with [`init`](Self::init) or
+    /// [`try_init`](Self::try_init). The global logger is never dropped, so call
+    /// `log::logger().flush()` before exiting to send any events which are still queued.
+    ///
+    /// Returns an error if the DSN is invalid. Requires the `sentry` feature.
+    #[cfg(feature = "sentry")]
+    pub fn with_sentry_integration(
+        mut self,
+        dsn: &str,
+    ) -> Result<Logger, sentry::types::ParseDsnError> {
+        self.sentry_dsn = if dsn.is_empty() { None } else { Some(self.quiet_on_err(dsn.parse())?) };
+        Ok(self)
+    }
+
+    /// Print a warning to stderr if this Logger is dropped without being registered with
+    /// [`init`](Self::init) or [`try_init`](Self::try_init), which catches mistakes like
+    /// `Logger::new().verbose(2);` where the configured logger is silently thrown away. Converting
+    /// the Logger with `into()` or [`into_arc`](Self::into_arc) also counts as initializing it.
+    ///
+    /// This is off by default because a Logger that's only used directly, e.g. with
+    /// [`format_record`](Self::format_record) or as a [`fmt::Write`], is never initialized. A
+    /// typical setup is `.with_drop_warn(cfg!(debug_assertions))` to check this in debug builds.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_drop_warn(mut self, enabled: bool) -> Logger {
+        self.drop_warn = enabled;
+        self
+    }
+
+    /// Write the process ID to `path` when the Logger is registered with [`init`](Self::init) or
+    /// [`try_init`](Self::try_init), as daemons conventionally do. The file is created or
+    /// truncated, and failing to write it is an initialization error.
+    ///
+    /// The file is removed when the Logger is dropped. Note that the [`log`](::log) crate never
+    /// drops the global logger, so a daemon which needs the file removed on a clean exit should
+    /// also delete it during its own shutdown.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_pid_file(mut self, path: impl AsRef<Path>) -> Logger {
+        self.pid_file = Some(path.as_ref().to_owned());
+        self
+    }
+
+    /// Limit the number of records queued for the worker when this Logger is used with an
+    /// [`AsyncLogger`](async_logger::AsyncLogger), so that a burst of logging can't use unbounded
+    /// memory. Once `max_queue` records are waiting, `policy` decides whether logging blocks
+    /// until the worker catches up or the record is dropped, see [`BackpressurePolicy`].
+    ///
+    /// This has no effect on a Logger which isn't used with an AsyncLogger.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_backpressure_limit(
+        mut self,
+        max_queue: usize,
+        policy: BackpressurePolicy,
+    ) -> Logger {
+        self.backpressure = Some((max_queue, policy));
+        self
+    }
+
+    /// The number of records dropped because of [`BackpressurePolicy::Drop`], same as
+    /// [`LogState::dropped_records`].
+    pub fn dropped_records(&self) -> u64 {
+        self.state.dropped_records()
+    }
+
+    /// Choose the kind of lock protecting the output stream. Only [`OutputLock::Mutex`] (the
+    /// default) is currently implemented, and [`build`](Self::build) returns
+    /// [`ConfigError::Unsupported`] for anything else. This exists so that applications can opt
+    /// in to an [`OutputLock::RwLock`] once read-only operations can take advantage of it.
+    #[must_use = "this builder method returns a new Logger; did you forget to assign it?"]
+    pub fn with_output_lock_type(mut self, lock: OutputLock) -> Logger {
+        self.output_lock = lock;
+        self
+    }
+
+    /// Register this as the global logger with the [`log`](::log) crate. May fail if the
+    /// configuration is invalid (see [`build`](Self::build)) or if the application has already
+    /// set a logger.
+    ///
+    /// For manual control over installation, e.g. to wrap yall in another logger, a Logger can be
+    /// converted into a `Box<dyn Log>` or `Arc<dyn Log + Send + Sync>` with `into()`. In that case
+    /// the startup header isn't written, and the caller is responsible for
+    /// [`log::set_max_level`].
+    pub fn try_init(self) -> Result<(), ConfigError> {
+        self.build().and_then(|l| l.try_init_inner())
+    }
+
+    /// Register this as the global logger without validating the configuration first.
+    fn try_init_inner(mut self) -> Result<(), ConfigError> {
+        if let Some(path) = &self.pid_file {
+            let res = fs::write(path, format!("{}\n", std::process::id()));
+            self.quiet_on_err(res)?;
+            self.pid_file_written = true;
+        }
+        #[cfg(feature = "sentry")]
+        if let Some(dsn) = self.sentry_dsn.take() {
+            let mut options = sentry::ClientOptions::default();
+            options.dsn = Some(dsn);
+            self.sentry = Some(sentry::init(options));
+        }
+        let init_message = self.init_message.take();
+        let too_verbose = match self.max_allowed_level {
+            Some(max) if self.level() > max => Some((self.level(), max)),
+            _ => None,
+        };
+        log::set_max_level(self.state.max_level());
+        self.initialized = true;
+        // register a leaked reference rather than a Box so that it can also be kept in REGISTERED
+        let logger: &'static Logger = Box::leak(Box::new(self));
+        if let Err(e) = log::set_logger(logger) {
+            // SAFETY: the logger wasn't registered, so this is the only reference to the leaked
+            // box and it can be reclaimed and dropped.
+            drop(unsafe { Box::from_raw(logger as *const Logger as *mut Logger) });
+            return Err(e.into());
+        }
+        // only one logger can ever be registered, so this is never already set
+        let _ = REGISTERED.set(logger);
+        // the header is only written once registration succeeded. Nothing at all is logged with
+        // the Off level, so the header would be noise too.
+        if logger.header && logger.state.max_level() != LevelFilter::Off {
+            // not much we can do if the header can't be written, and the same error will happen
+            // again and get reported on the first log message.
+            if let Ok(mut out) = logger.lock_out() {
+                let _ = logger.write_header(&mut *out);
+            }
+        }
+        if let Some((msg, level)) = init_message {
+            log::log!(level, "{}", msg);
+        }
+        if let Some((level, max)) = too_verbose {
+            log::warn!(
+                "log level {} is more verbose than the expected maximum of {}",
+                DisplayLevel(level),
+                DisplayLevel(max)
+            );
+        }
+        Ok(())
+    }
+
+    /// Wrap this Logger in an [`Arc`] so that a handle to it can be kept after it's installed.
+    /// The [`log`](::log) crate implements [`Log`] for any `Arc<T: Log>` by delegating to the
+    /// inner logger, so a clone can be registered with
+    /// `log::set_boxed_logger(Box::new(Arc::clone(&logger)))` while the original is kept for
+    /// methods like [`write_separator`](Self::write_separator) and
+    /// [`would_filter`](Self::would_filter).
+    ///
+    /// As with the `into()` conversions, the startup header isn't written and the caller is
+    /// responsible for [`log::set_max_level`].
+    pub fn into_arc(mut self) -> Arc<Logger> {
+        self.initialized = true;
+        Arc::new(self)
+    }
+
+    /// Same as [`try_init`](Self::try_init), but on success return a handle to the logger's
+    /// [`LogState`], which can be used to inspect the logger after it's been registered.
+    pub fn try_init_and_return(self) -> Result<Arc<LogState>, ConfigError> {
+        let state = Arc::clone(&self.state);
+        self.try_init()?;
+        Ok(state)
+    }
+
+    /// Same as [`try_init`](Self::try_init) but panic on failure.
+    pub fn init(self) {
+        self.build()
+            .expect("invalid logger configuration")
+            .try_init_inner()
+            .expect("failed to initialize logger");
+    }
+
+    /// Internal wrapper function for the meat of the logging that returns a Result, in case the
+    /// termcolors printing fails somehow. Assumes that we've already checked that the record's
+    /// log level is in fact enabled.
+    fn print_log(&self, r: &Record) -> io::Result<()> {
+        let sentinel = match &self.write_sentinel {
+            Some(s) => s,
+            None => return self.write_log(r),
+        };
+        // The sentinel is held while writing, so if it's already locked then another thread is
+        // in the middle of a write. Either way, this write still waits for the output lock.
+        match sentinel.try_lock() {
+            Ok(_guard) => self.write_log(r),
+            Err(TryLockError::Poisoned(e)) => {
+                let _guard = e.into_inner();
+                self.write_log(r)
+            }
+            Err(TryLockError::WouldBlock) => self
+                .write_log(&with_args(r, format_args!("{} [concurrent-write-detected]", r.args()))),
+        }
+    }
+
+    fn write_log(&self, r: &Record) -> io::Result<()> {
+        let mut out = self.lock_output(self.output_for(r.level()))?;
+        if self.line_buffered || self.ascii_only || self.transcoding() {
+            let mut buf = if out.supports_color() { Buffer::ansi() } else { Buffer::no_color() };
+            self.print_log_to(r, &mut buf)?;
+            // format as UTF-8 first, escape sequences are plain ASCII so escaping and transcoding
+            // leave them alone
+            let mut text = String::from_utf8_lossy(buf.as_slice());
+            if self.ascii_only {
+                text = Cow::Owned(escape_non_ascii(&text));
+            }
+            out.write_all(&self.encode(&text))?;
+        } else {
+            self.print_log_to(r, &mut *out)?;
+        }
+        // the output could have been changed since with_terminal_title_update checked stderr
+        if self.terminal_title && self.output_is_stderr && r.level() == Level::Error {
+            // the title is cosmetic, so don't bother reporting errors
+            let _ = write_terminal_title(&mut *out, r);
+        }
+        match self.flush_level {
+            Some(level) if r.level() <= level => out.flush(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether output is converted to an encoding other than UTF-8.
+    fn transcoding(&self) -> bool {
+        #[cfg(feature = "encoding")]
+        {
+            self.encoding != OutputEncoding::Utf8
+        }
+        #[cfg(not(feature = "encoding"))]
+        {
+            false
+        }
+    }
+
+    /// Convert formatted output to the output encoding.
+    fn encode<'a>(&self, text: &'a str) -> Cow<'a, [u8]> {
+        #[cfg(feature = "encoding")]
+        {
+            encoding::transcode(text, self.encoding)
+        }
+        #[cfg(not(feature = "encoding"))]
+        {
+            Cow::Borrowed(text.as_bytes())
+        }
+    }
+
+    /// Pass through the result of a fallible builder step, disabling the
+    /// [drop warning](Self::with_drop_warn) if it failed. The Logger is about to be dropped, but
+    /// the error is reported to the caller so there's no need to also warn about it.
+    fn quiet_on_err<T, E>(&mut self, res: Result<T, E>) -> Result<T, E> {
+        if res.is_err() {
+            self.drop_warn = false;
+        }
+        res
+    }
+
+    /// Re-create the stderr output stream after changing its settings. Does nothing if the output
+    /// has been replaced with something other than stderr.
+    fn reopen_stderr(&mut self) {
+        if self.output_is_stderr {
+            self.out = Mutex::new(self.stderr_output());
+        }
+        // the fallback is stderr too, so it needs the same color choice
+        if self.fallback.is_some() {
+            self.fallback = Some(Mutex::new(StandardStream::stderr(self.color_choice)));
+        }
+    }
+
+    /// Create a stderr output stream, which is buffered if flush_on_level was used, and also
+    /// writes to the file from with_output_to_stderr_and_file if there is one.
+    fn stderr_output(&self) -> Output {
+        let term: Output = if self.flush_level.is_some() {
+            Box::new(BufferedStandardStream::stderr(self.color_choice))
+        } else {
+            Box::new(StandardStream::stderr(self.color_choice))
+        };
+        match &self.tee_file {
+            Some(file) => Box::new(Tee { term, file: Arc::clone(file), file_failed: false }),
+            None => term,
+        }
+    }
+
+    /// Recover the output lock after a panic if that's enabled, otherwise panic as well.
+    fn recover_poison<'a>(
+        &self,
+        out: &'a Mutex<Output>,
+        e: PoisonError<MutexGuard<'a, Output>>,
+    ) -> MutexGuard<'a, Output> {
+        if !self.recover_poison {
+            panic!("{}", e);
+        }
+        let mut guard = e.into_inner();
+        out.clear_poison();
+        if !self.poison_warned.swap(true, Ordering::Relaxed) {
+            let _ = writeln!(guard, "yall: recovered the output lock after a panic while logging");
+        }
+        guard
+    }
+
+    /// Lock the main output stream.
+    fn lock_out(&self) -> io::Result<MutexGuard<'_, Output>> {
+        self.lock_output(&self.out)
+    }
+
+    /// The output stream for records at `level`, which is the main one unless
+    /// [`with_priority_output`](Self::with_priority_output) was used.
+    fn output_for(&self, level: Level) -> &Mutex<Output> {
+        match &self.low_out {
+            Some((threshold, low)) if level > *threshold => low,
+            _ => &self.out,
+        }
+    }
+
+    /// Lock an output stream, respecting the lock timeout if one is set. std's Mutex has no
+    /// timed lock, so poll with try_lock until the deadline passes.
+    fn lock_output<'a>(&self, out: &'a Mutex<Output>) -> io::Result<MutexGuard<'a, Output>> {
+        let timeout = match self.lock_timeout {
+            Some(t) => t,
+            None => return Ok(out.lock().unwrap_or_else(|e| self.recover_poison(out, e))),
+        };
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            match out.try_lock() {
+                Ok(guard) => return Ok(guard),
+                Err(TryLockError::Poisoned(e)) => return Ok(self.recover_poison(out, e)),
+                Err(TryLockError::WouldBlock) => {
+                    if Instant::now() >= deadline {
+                        return Err(io::Error::new(
+                            io::ErrorKind::TimedOut,
+                            "timed out waiting for the output lock, possible deadlock",
+                        ));
+                    }
+                    thread::sleep(Duration::from_millis(1));
+                }
+            }
+        }
+    }
+}
+
+/// Each `write!` or `writeln!` call is logged as a separate message at the level set with
+/// [`Logger::write_level`], with the trailing newline from `writeln!` removed. Level filtering
+/// still applies.
+impl fmt::Write for Logger {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_at_level(self.write_level, s.strip_suffix('\n').unwrap_or(s));
+        Ok(())
+    }
+
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> fmt::Result {
+        // format the whole message first, otherwise each piece would be a separate message
+        match args.as_str() {
+            Some(s) => self.write_str(s),
+            None => self.write_str(&args.to_string()),
+        }
+    }
+}
+
+/// Copy a record with new message arguments, for when yall needs to modify the message.
+fn with_args<'a>(r: &'a Record<'a>, args: fmt::Arguments<'a>) -> Record<'a> {
+    let mut builder = Record::builder();
+    builder
+        .metadata(r.metadata().clone())
+        .module_path(r.module_path())
+        .file(r.file())
+        .line(r.line())
+        .args(args);
+    #[cfg(feature = "kv")]
+    builder.key_values(r.key_values());
+    builder.build()
+}
+
+/// Set the terminal title to an error message. Control characters would end the escape sequence
+/// early, so they're replaced with spaces.
+fn write_terminal_title(out: &mut dyn Write, r: &Record) -> io::Result<()> {
+    let msg: String = r
+        .args()
+        .to_string()
+        .chars()
+        .take(60)
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    write!(out, "\x1b]0;ERROR: {}\x07", msg)
+}
+
+/// Emit a log record as an event to the current `tracing` subscriber. The `event!` macro needs a
+/// constant level, hence the repetition.
+#[cfg(feature = "tracing")]
+fn tracing_event(r: &Record) {
+    macro_rules! event {
+        ($level:expr) => {
+            tracing::event!(
+                $level,
+                log.target = r.target(),
+                log.file = r.file(),
+                log.line = r.line(),
+                "{}",
+                r.args()
+            )
+        };
+    }
+
+    match r.level() {
+        Level::Error => event!(tracing::Level::ERROR),
+        Level::Warn => event!(tracing::Level::WARN),
+        Level::Info => event!(tracing::Level::INFO),
+        Level::Debug => event!(tracing::Level::DEBUG),
+        Level::Trace => event!(tracing::Level::TRACE),
+    }
+}
+
+impl From<Logger> for Box<dyn Log> {
+    fn from(mut logger: Logger) -> Self {
+        logger.initialized = true;
+        Box::new(logger)
+    }
+}
+
+impl From<Logger> for Arc<dyn Log + Send + Sync> {
+    fn from(mut logger: Logger) -> Self {
+        logger.initialized = true;
+        Arc::new(logger)
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, m: &Metadata) -> bool {
+        let mut level =
+            if self.has_overrides() { self.level_for(m.target()) } else { self.level() };
+        if let Some(signal) = &self.verbose_signal {
+            if signal.load(Ordering::Relaxed) {
+                level = level.max(LevelFilter::Info);
+            }
+        }
+        m.level() <= level
+    }
+
+    fn log(&self, r: &Record) {
+        if let Some(recent) = &self.recent {
+            recent.push_record(r, &self.format);
+        }
+        if !self.enabled(r.metadata()) {
+            if self.count_filtered {
+                SUPPRESSED[r.level() as usize - 1].fetch_add(1, Ordering::Relaxed);
+            }
+            // records which were written already don't need to be repeated as context
+            if let Some(context) = &self.error_context {
+                if r.level() != Level::Error && context.depth > 0 {
+                    let mut records = context.records.lock().unwrap_or_else(|e| e.into_inner());
+                    if records.len() == context.depth {
+                        records.pop_front();
+                    }
+                    records.push_back(ContextRecord {
+                        target: r.target().to_owned(),
+                        file: r.file().map(String::from),
+                        line: r.line(),
+                        msg: r.args().to_string(),
+                    });
+                }
+            }
+            return;
+        }
+        if r.level() == Level::Error {
+            self.write_error_context();
+        }
+
+        let idx = r.level() as usize - 1;
+        let rate = self.sample_rates[idx];
+        if rate > 1 {
+            let phase = self.sample_counters[idx].fetch_add(1, Ordering::Relaxed) % u64::from(rate);
+            if phase != 0 {
+                return;
+            }
+            self.emit(&with_args(r, format_args!("{} (sampled 1/{})", r.args(), rate)));
+        } else {
+            self.emit(r);
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut out) = self.lock_out() {
+            if self.count_filtered {
+                let _ = writeln!(out, "{}", suppression_summary());
+            }
+            let _ = out.flush();
+        }
+        if let Some((_, low)) = &self.low_out {
+            if let Ok(mut out) = self.lock_output(low) {
+                let _ = out.flush();
+            }
+        }
+        #[cfg(feature = "sentry")]
+        if self.sentry.is_some() {
+            if let Some(client) = sentry::Hub::current().client() {
+                client.flush(None);
+            }
+        }
+    }
+}
+
+impl Logger {
+    /// Write out and clear the records saved by with_error_context_capture, at Error level with
+    /// a `[context]` tag. These aren't counted as errors, and write errors are ignored since the
+    /// error record itself will report them.
+    fn write_error_context(&self) {
+        let context = match &self.error_context {
+            Some(c) => c,
+            None => return,
+        };
+        let records: Vec<ContextRecord> =
+            context.records.lock().unwrap_or_else(|e| e.into_inner()).drain(..).collect();
+        for c in records.iter() {
+            let _ = self.print_log(
+                &Record::builder()
+                    .level(Level::Error)
+                    .target(&c.target)
+                    .file(c.file.as_deref())
+                    .line(c.line)
+                    .args(format_args!("[context] {}", c.msg))
+                    .build(),
+            );
+        }
+    }
+
+    /// Write a record which has passed filtering and sampling.
+    fn emit(&self, r: &Record) {
+        if let Some(f) = self.first_record_action {
+            self.first_record_once.call_once(|| f(r));
+        }
+        let count = self.state.increment(r.level());
+        for (level, watermark, action) in self.watermarks.iter() {
+            // the count only passes each value once, so each action is called exactly once
+            if *level == r.level() && *watermark == count {
+                action(count);
+            }
+        }
+        if let Some(store) = &self.last_error {
+            if r.level() == Level::Error {
+                let msg = r.args().to_string();
+                // a poisoned lock only means another thread panicked while storing a string
+                *store.lock().unwrap_or_else(|e| e.into_inner()) = Some(msg);
+            }
+        }
+
+        #[cfg(feature = "sentry")]
+        if self.sentry.is_some() && r.level() <= Level::Warn {
+            let level = if r.level() == Level::Error {
+                sentry::Level::Error
+            } else {
+                sentry::Level::Warning
+            };
+            sentry::capture_message(&r.args().to_string(), level);
+        }
+
+        #[cfg(feature = "tracing")]
+        if self.forward_to_tracing {
+            tracing_event(r);
+            return;
+        }
+
+        let err = match self.print_log(r) {
+            Ok(()) => {
+                self.in_fallback.store(false, Ordering::Relaxed);
+                return;
+            }
+            Err(e) => e,
+        };
+
+        if let Some(fallback) = &self.fallback {
+            let mut stderr = fallback.lock().unwrap_or_else(PoisonError::into_inner);
+            let mut res = Ok(());
+            if !self.in_fallback.swap(true, Ordering::Relaxed) {
+                res = writeln!(stderr, "yall: failed to write log output ({}), using stderr", err);
+            }
+            if res.and_then(|_| write_record(r, &self.format, &mut *stderr)).is_ok() {
+                return;
+            }
+        }
+
+        // uh oh, something in termcolor failed
+        if self.panic_on_failure {
+            panic!("failed to write log message because of '{}'", err);
+        }
+        if self.eprint_on_failure {
+            eprintln!("LOGGING ERROR: failed to write log message because of '{}'", err);
+            eprintln!("Original message: {}: {}", r.level(), r.args());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_escapes_plain() {
+        assert!(matches!(strip_escapes(b"no escapes"), Cow::Borrowed(b"no escapes")));
+    }
+
+    #[test]
+    fn strip_escapes_colors() {
+        let colored = b"\x1b[0m\x1b[1m\x1b[31m[ERROR]\x1b[0m failed\n";
+        assert_eq!(&*strip_escapes(colored), b"[ERROR] failed\n");
+        assert_eq!(&*strip_escapes(b"\x1b[38;5;208mx\x1b[m"), b"x");
+    }
+
+    #[test]
+    fn strip_escapes_osc() {
+        let link = b"\x1b]8;;file:///src/main.rs\x1b\\[main:3]\x1b]8;;\x1b\\ msg";
+        assert_eq!(&*strip_escapes(link), b"[main:3] msg");
+        assert_eq!(&*strip_escapes(b"\x1b]0;ERROR: title\x07rest"), b"rest");
+    }
+
+    #[test]
+    fn strip_escapes_truncated() {
+        assert_eq!(&*strip_escapes(b"a\x1b"), b"a");
+        assert_eq!(&*strip_escapes(b"a\x1b[31"), b"a");
+        assert_eq!(&*strip_escapes(b"a\x1b]0;unterminated"), b"a");
+        assert_eq!(&*strip_escapes(b"a\x1bxb"), b"axb");
     }
 }
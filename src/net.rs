@@ -0,0 +1,183 @@
+// Copyright (c) 2020 Allen Wild
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Network outputs, for [`Logger::with_output_to_udp`](crate::Logger::with_output_to_udp) and
+//! [`Logger::with_output_to_tcp`](crate::Logger::with_output_to_tcp).
+
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The largest datagram sent, which leaves room for IP and UDP headers in the 64 KiB limit.
+const MAX_DATAGRAM: usize = 65000;
+
+/// A writer which sends each line as a single UDP datagram. Writes are collected until one ends
+/// with a newline, so that output written in pieces (like the startup header) isn't split into
+/// several datagrams. The Logger formats each record into a buffer and writes it at once, so a
+/// multi-line message is still sent as one datagram.
+pub(crate) struct UdpWriter {
+    socket: UdpSocket,
+    addr: SocketAddr,
+    pending: Vec<u8>,
+}
+
+impl UdpWriter {
+    pub(crate) fn new(addr: SocketAddr) -> io::Result<UdpWriter> {
+        let bind_addr: SocketAddr =
+            if addr.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+        let socket = UdpSocket::bind(bind_addr)?;
+        // never wait on the network, a full socket buffer means the record is lost like any
+        // other dropped datagram
+        socket.set_nonblocking(true)?;
+        Ok(UdpWriter { socket, addr, pending: Vec::new() })
+    }
+}
+
+impl Write for UdpWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // anything past the datagram limit would be truncated anyway
+        let room = MAX_DATAGRAM.saturating_sub(self.pending.len());
+        self.pending.extend_from_slice(&buf[..buf.len().min(room)]);
+        if !buf.ends_with(b"\n") {
+            return Ok(buf.len());
+        }
+        let res = self.socket.send_to(&self.pending, self.addr);
+        self.pending.clear();
+        match res {
+            Ok(_) => Ok(buf.len()),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(buf.len()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The longest wait between reconnection attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// How long to wait for each connection attempt, since the logging thread is blocked meanwhile.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A writer which sends newline-delimited log lines over TCP. If the connection fails and
+/// `reconnect` is set, lines are queued (up to `capacity`, after which new lines are dropped)
+/// while reconnecting with exponential backoff. Like [`UdpWriter`], writes are collected until
+/// one ends with a newline, so the queue holds whole lines.
+pub(crate) struct TcpWriter {
+    addr: SocketAddr,
+    stream: Option<TcpStream>,
+    pending: Vec<u8>,
+    reconnect: bool,
+    queue: VecDeque<Vec<u8>>,
+    capacity: Arc<AtomicUsize>,
+    backoff: Duration,
+    next_attempt: Instant,
+}
+
+impl TcpWriter {
+    pub(crate) fn connect(
+        addr: SocketAddr,
+        reconnect: bool,
+        capacity: Arc<AtomicUsize>,
+    ) -> io::Result<TcpWriter> {
+        let stream = match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+            Ok(stream) => Some(stream),
+            // the receiver may not be up yet, so keep trying in the background
+            Err(_) if reconnect => None,
+            Err(e) => return Err(e),
+        };
+        Ok(TcpWriter {
+            addr,
+            stream,
+            pending: Vec::new(),
+            reconnect,
+            queue: VecDeque::new(),
+            capacity,
+            backoff: Duration::from_secs(1),
+            next_attempt: Instant::now(),
+        })
+    }
+
+    /// Try to reconnect if it's time to, then send any queued lines.
+    fn try_reconnect(&mut self) {
+        if Instant::now() < self.next_attempt {
+            return;
+        }
+        match TcpStream::connect_timeout(&self.addr, CONNECT_TIMEOUT) {
+            Ok(stream) => {
+                self.stream = Some(stream);
+                self.backoff = Duration::from_secs(1);
+                while let Some(line) = self.queue.pop_front() {
+                    if self.send(&line).is_err() {
+                        self.queue.push_front(line);
+                        self.lost_connection();
+                        return;
+                    }
+                }
+            }
+            Err(_) => {
+                self.next_attempt = Instant::now() + self.backoff;
+                self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+
+    fn lost_connection(&mut self) {
+        self.stream = None;
+        self.next_attempt = Instant::now() + self.backoff;
+    }
+
+    fn send(&mut self, line: &[u8]) -> io::Result<()> {
+        match &mut self.stream {
+            Some(stream) => stream.write_all(line),
+            None => Err(io::ErrorKind::NotConnected.into()),
+        }
+    }
+
+    fn enqueue(&mut self, line: Vec<u8>) {
+        if self.queue.len() < self.capacity.load(Ordering::Relaxed) {
+            self.queue.push_back(line);
+        }
+    }
+}
+
+impl Write for TcpWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        if !buf.ends_with(b"\n") {
+            return Ok(buf.len());
+        }
+        let line = std::mem::take(&mut self.pending);
+
+        if self.stream.is_none() && self.reconnect {
+            self.try_reconnect();
+        }
+        if self.stream.is_none() {
+            if !self.reconnect {
+                return Err(io::ErrorKind::NotConnected.into());
+            }
+            self.enqueue(line);
+            return Ok(buf.len());
+        }
+        if let Err(e) = self.send(&line) {
+            self.lost_connection();
+            if !self.reconnect {
+                return Err(e);
+            }
+            self.enqueue(line);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.stream {
+            Some(stream) => stream.flush(),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,129 @@
+// Copyright (c) 2020 Allen Wild
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! A logger which keeps the most recent log lines in memory rather than writing them anywhere.
+//!
+//! This is useful for daemons and embedded applications which want the last few log lines
+//! available for diagnostics (e.g. through a status API) without writing to disk.
+//!
+//! The underlying [`RingBuffer`] can also be attached to a regular [`Logger`](crate::Logger) with
+//! [`Logger::with_last_n_records`](crate::Logger::with_last_n_records).
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+use termcolor::NoColor;
+
+use crate::{write_record, FormatConfig};
+
+/// A buffer of the last `capacity` formatted log lines, without colors or trailing newlines.
+/// Once full, the oldest line is dropped for each new line.
+#[derive(Debug)]
+pub struct RingBuffer {
+    capacity: usize,
+    lines: Mutex<VecDeque<String>>,
+}
+
+impl RingBuffer {
+    /// Create an empty RingBuffer which holds up to `capacity` lines.
+    pub fn new(capacity: usize) -> RingBuffer {
+        RingBuffer { capacity, lines: Mutex::new(VecDeque::with_capacity(capacity)) }
+    }
+
+    /// Get a copy of the stored lines, oldest first.
+    pub fn snapshot(&self) -> Vec<String> {
+        self.lines.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Remove and return all the stored lines, oldest first.
+    pub fn drain(&self) -> Vec<String> {
+        self.lines.lock().unwrap().drain(..).collect()
+    }
+
+    /// Format a record and add it to the buffer.
+    pub(crate) fn push_record(&self, r: &Record, format: &FormatConfig) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut buf = NoColor::new(Vec::new());
+        // writing to a Vec can't fail
+        let _ = write_record(r, format, &mut buf);
+        let mut line = String::from_utf8_lossy(buf.get_ref()).into_owned();
+        if line.ends_with('\n') {
+            line.pop();
+        }
+
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() == self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+}
+
+/// A logger which stores the last `capacity` formatted log lines in a [`RingBuffer`].
+#[derive(Debug)]
+pub struct RingLogger {
+    level: LevelFilter,
+    format: FormatConfig,
+    buffer: RingBuffer,
+}
+
+impl RingLogger {
+    /// Create a RingLogger which holds up to `capacity` lines, with the default Info level.
+    pub fn new(capacity: usize) -> RingLogger {
+        RingLogger {
+            level: LevelFilter::Info,
+            format: FormatConfig::new(),
+            buffer: RingBuffer::new(capacity),
+        }
+    }
+
+    /// Set the log level.
+    pub fn level(mut self, level: LevelFilter) -> RingLogger {
+        self.level = level;
+        self
+    }
+
+    /// Set the formatting options used for each line.
+    pub fn format(mut self, format: FormatConfig) -> RingLogger {
+        self.format = format;
+        self
+    }
+
+    /// Get a copy of the stored lines, oldest first.
+    pub fn snapshot(&self) -> Vec<String> {
+        self.buffer.snapshot()
+    }
+
+    /// Remove and return all the stored lines, oldest first.
+    pub fn drain(&self) -> Vec<String> {
+        self.buffer.drain()
+    }
+
+    /// Register this as the global logger with the [`log`](::log) crate. May fail if the
+    /// application has already set a logger.
+    ///
+    /// Since this consumes the RingLogger, use `log::set_logger` with a `&'static RingLogger`
+    /// directly to keep access to [`snapshot`](Self::snapshot) and [`drain`](Self::drain).
+    pub fn try_init(self) -> Result<(), SetLoggerError> {
+        log::set_max_level(self.level);
+        log::set_boxed_logger(Box::new(self))
+    }
+}
+
+impl Log for RingLogger {
+    fn enabled(&self, m: &Metadata) -> bool {
+        m.level() <= self.level
+    }
+
+    fn log(&self, r: &Record) {
+        if self.enabled(r.metadata()) {
+            self.buffer.push_record(r, &self.format);
+        }
+    }
+
+    fn flush(&self) {}
+}
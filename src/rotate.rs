@@ -0,0 +1,149 @@
+// Copyright (c) 2020 Allen Wild
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Log file rotation for [`Logger::with_rotating_file`](crate::Logger::with_rotating_file).
+//!
+//! The current log is always written to [`RotateConfig::path`]. When it's rotated, older files
+//! are shifted to `path.1`, `path.2`, and so on up to `keep_count`, and the oldest is deleted.
+//! With `compress_old`, rotated files are gzipped and named `path.1.gz` etc.
+
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+#[cfg(feature = "compress")]
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Settings for a rotating log file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RotateConfig {
+    /// The log file to write to.
+    pub path: PathBuf,
+    /// Rotate once the file reaches this size.
+    pub max_size_bytes: Option<u64>,
+    /// Rotate once the file has been open for this long.
+    pub max_age: Option<Duration>,
+    /// The number of rotated files to keep, not counting the current one.
+    pub keep_count: u32,
+    /// Gzip rotated files. Requires the `compress` feature.
+    pub compress_old: bool,
+}
+
+impl RotateConfig {
+    /// Create a config for `path` which never rotates on its own and keeps 5 old files. Set
+    /// `max_size_bytes` and/or `max_age` to enable rotation.
+    pub fn new(path: impl Into<PathBuf>) -> RotateConfig {
+        RotateConfig {
+            path: path.into(),
+            max_size_bytes: None,
+            max_age: None,
+            keep_count: 5,
+            compress_old: false,
+        }
+    }
+
+    /// The name of the `n`th rotated file.
+    fn rotated_path(&self, n: u32) -> PathBuf {
+        let mut name = OsString::from(self.path.as_os_str());
+        name.push(format!(".{}", n));
+        if self.compress_old {
+            name.push(".gz");
+        }
+        PathBuf::from(name)
+    }
+}
+
+/// A file writer which rotates itself according to a [`RotateConfig`]. Rotation is only checked
+/// after writes that end with a newline, so that log lines aren't split between files.
+pub(crate) struct RotatingFile {
+    config: RotateConfig,
+    file: File,
+    size: u64,
+    opened: Instant,
+    rotate_failed: bool,
+}
+
+impl RotatingFile {
+    pub(crate) fn open(config: RotateConfig) -> io::Result<RotatingFile> {
+        if config.compress_old && !cfg!(feature = "compress") {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "compressing rotated logs requires yall's compress feature",
+            ));
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&config.path)?;
+        let size = file.metadata()?.len();
+        Ok(RotatingFile { config, file, size, opened: Instant::now(), rotate_failed: false })
+    }
+
+    fn should_rotate(&self) -> bool {
+        self.config.max_size_bytes.is_some_and(|max| self.size >= max)
+            || self.config.max_age.is_some_and(|max| self.opened.elapsed() >= max)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let keep = self.config.keep_count;
+        if keep > 0 {
+            // shift path.N-1 to path.N and so on, overwriting the oldest
+            for n in (1..keep).rev() {
+                let from = self.config.rotated_path(n);
+                if from.exists() {
+                    fs::rename(&from, self.config.rotated_path(n + 1))?;
+                }
+            }
+            // RotatingFile::open rejects compress_old without the compress feature
+            #[cfg(feature = "compress")]
+            if self.config.compress_old {
+                compress(&self.config.path, &self.config.rotated_path(1))?;
+                fs::remove_file(&self.config.path)?;
+            } else {
+                fs::rename(&self.config.path, self.config.rotated_path(1))?;
+            }
+            #[cfg(not(feature = "compress"))]
+            fs::rename(&self.config.path, self.config.rotated_path(1))?;
+        }
+        // with keep_count 0 the old log is simply truncated
+        self.file =
+            OpenOptions::new().create(true).write(true).truncate(true).open(&self.config.path)?;
+        self.size = 0;
+        self.opened = Instant::now();
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.file.write(buf)?;
+        self.size += n as u64;
+        if buf[..n].ends_with(b"\n") && self.should_rotate() {
+            // The data was already written, so returning an error would make the caller think
+            // it wasn't. Report the failure separately and keep using the current file, trying
+            // again after the next line.
+            match self.rotate() {
+                Ok(()) => self.rotate_failed = false,
+                Err(e) if !self.rotate_failed => {
+                    self.rotate_failed = true;
+                    eprintln!("yall: failed to rotate log file: {}", e);
+                }
+                Err(_) => (),
+            }
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(feature = "compress")]
+fn compress(src: &Path, dest: &Path) -> io::Result<()> {
+    use flate2::{write::GzEncoder, Compression};
+
+    let mut encoder = GzEncoder::new(File::create(dest)?, Compression::default());
+    io::copy(&mut File::open(src)?, &mut encoder)?;
+    encoder.finish()?;
+    Ok(())
+}
@@ -0,0 +1,70 @@
+// Copyright (c) 2020 Allen Wild
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Named color scheme presets for use with [`Logger::color_scheme`](crate::Logger::color_scheme).
+//!
+//! yall's default colors use the standard terminal palette, so they follow whatever theme the
+//! terminal is configured with. The other presets use RGB colors taken from popular editor and
+//! terminal themes, which look best when the terminal background matches that theme.
+
+use termcolor::{Color, ColorSpec};
+
+use crate::{Level, LogColors};
+
+/// A named set of colors for each log level.
+#[derive(Debug, Clone)]
+pub enum ColorScheme {
+    /// yall's standard colors using the terminal's basic palette.
+    Default,
+    /// Accent colors from the Solarized theme.
+    Solarized,
+    /// Accent colors from the Gruvbox dark theme.
+    Gruvbox,
+    /// Accent colors from the Monokai theme.
+    Monokai,
+    /// Accent colors from the Dracula theme.
+    Dracula,
+    /// User-defined colors, see [`ColorScheme::custom`].
+    Custom(LogColors),
+}
+
+impl Default for ColorScheme {
+    /// The default ColorScheme is `Default`
+    fn default() -> Self {
+        Self::Default
+    }
+}
+
+/// Build a LogColors from RGB values for error, warn, debug, and trace. Info is always plain, and
+/// error and warn are bold, the same as the default colors.
+fn rgb_colors(error: u32, warn: u32, debug: u32, trace: u32) -> LogColors {
+    fn rgb(c: u32) -> Option<Color> {
+        Some(Color::Rgb((c >> 16) as u8, (c >> 8) as u8, c as u8))
+    }
+
+    let mut colors = LogColors::new();
+    colors.set(Level::Error, ColorSpec::new().set_fg(rgb(error)).set_bold(true).to_owned());
+    colors.set(Level::Warn, ColorSpec::new().set_fg(rgb(warn)).set_bold(true).to_owned());
+    colors.set(Level::Debug, ColorSpec::new().set_fg(rgb(debug)).to_owned());
+    colors.set(Level::Trace, ColorSpec::new().set_fg(rgb(trace)).to_owned());
+    colors
+}
+
+impl ColorScheme {
+    /// Create a scheme from user-defined colors.
+    pub fn custom(colors: LogColors) -> ColorScheme {
+        ColorScheme::Custom(colors)
+    }
+
+    /// Get the colors for this scheme.
+    pub fn into_colors(self) -> LogColors {
+        match self {
+            ColorScheme::Default => LogColors::new(),
+            ColorScheme::Solarized => rgb_colors(0xdc322f, 0xb58900, 0x2aa198, 0x268bd2),
+            ColorScheme::Gruvbox => rgb_colors(0xfb4934, 0xfabd2f, 0x8ec07c, 0x83a598),
+            ColorScheme::Monokai => rgb_colors(0xf92672, 0xe6db74, 0x66d9ef, 0xae81ff),
+            ColorScheme::Dracula => rgb_colors(0xff5555, 0xf1fa8c, 0x8be9fd, 0xbd93f9),
+            ColorScheme::Custom(colors) => colors,
+        }
+    }
+}
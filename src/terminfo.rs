@@ -0,0 +1,104 @@
+// Copyright (c) 2020 Allen Wild
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Terminal color depth detection using terminfo, for
+//! `Logger::with_color_support_detection_via_terminfo`.
+
+use log::Level;
+use termcolor::Color;
+
+use crate::LogColors;
+
+/// How many colors the terminal supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ColorDepth {
+    /// No color support at all.
+    None,
+    /// The 8 (or 16) standard ANSI colors.
+    Basic,
+    /// The 256-color palette.
+    Ansi256,
+    /// 24-bit RGB color.
+    TrueColor,
+}
+
+/// Look up the color depth of the current terminal from `$TERM`'s terminfo entry, or None if
+/// terminfo isn't available. `COLORTERM=truecolor` is also checked since many terminals which
+/// support 24-bit color don't advertise it in terminfo.
+pub(crate) fn detect() -> Option<ColorDepth> {
+    let info = term::terminfo::TermInfo::from_env().ok()?;
+    let colors = info.numbers.get("colors").copied().unwrap_or(0);
+    let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+    Some(if colors >= 1 << 24 || colorterm == "truecolor" || colorterm == "24bit" {
+        ColorDepth::TrueColor
+    } else if colors >= 256 {
+        ColorDepth::Ansi256
+    } else if colors >= 8 {
+        ColorDepth::Basic
+    } else {
+        ColorDepth::None
+    })
+}
+
+/// Convert any colors which need more than `depth` to the closest supported color.
+pub(crate) fn downgrade(colors: &LogColors, depth: ColorDepth) -> LogColors {
+    let mut new = colors.clone();
+    for level in Level::iter() {
+        let mut spec = colors.get(level).clone();
+        let fg = spec.fg().map(|c| downgrade_color(*c, depth));
+        let bg = spec.bg().map(|c| downgrade_color(*c, depth));
+        new.set(level, spec.set_fg(fg).set_bg(bg).clone());
+    }
+    new
+}
+
+fn downgrade_color(color: Color, depth: ColorDepth) -> Color {
+    match (color, depth) {
+        (Color::Rgb(r, g, b), ColorDepth::Ansi256) => {
+            // the 6x6x6 color cube starts at index 16
+            let scale = |x: u8| ((u16::from(x) * 5 + 127) / 255) as u8;
+            Color::Ansi256(16 + 36 * scale(r) + 6 * scale(g) + scale(b))
+        }
+        (Color::Rgb(r, g, b), ColorDepth::Basic | ColorDepth::None) => basic_color(r, g, b),
+        (Color::Ansi256(n), ColorDepth::Basic | ColorDepth::None) => {
+            let (r, g, b) = ansi256_to_rgb(n);
+            basic_color(r, g, b)
+        }
+        (color, _) => color,
+    }
+}
+
+/// The closest of the 8 basic colors, by turning each channel on if it's more than half bright.
+fn basic_color(r: u8, g: u8, b: u8) -> Color {
+    match (r > 127, g > 127, b > 127) {
+        (false, false, false) => Color::Black,
+        (true, false, false) => Color::Red,
+        (false, true, false) => Color::Green,
+        (true, true, false) => Color::Yellow,
+        (false, false, true) => Color::Blue,
+        (true, false, true) => Color::Magenta,
+        (false, true, true) => Color::Cyan,
+        (true, true, true) => Color::White,
+    }
+}
+
+/// Approximate RGB value of an entry in the 256-color palette.
+fn ansi256_to_rgb(n: u8) -> (u8, u8, u8) {
+    match n {
+        // the basic colors, where 8-15 are the bright versions
+        0..=15 => {
+            let on = if n >= 8 { 255 } else { 192 };
+            let bit = |b: u8| if n & b != 0 { on } else { 0 };
+            (bit(1), bit(2), bit(4))
+        }
+        16..=231 => {
+            let n = n - 16;
+            let level = |x: u8| if x == 0 { 0 } else { 55 + x * 40 };
+            (level(n / 36), level((n / 6) % 6), level(n % 6))
+        }
+        _ => {
+            let gray = 8 + (n - 232) * 10;
+            (gray, gray, gray)
+        }
+    }
+}